@@ -0,0 +1,36 @@
+//! Compare parsing a large expression into boxed AST nodes and into an
+//! `ExprArena`. Run with `cargo run --release --example arena_bench`.
+
+use std::time::{Duration, Instant};
+
+use rucky::arena::{balanced_expr, ArenaParser};
+use rucky::lexer::Lexer;
+use rucky::parser::Parser;
+
+const DEPTH: usize = 16;
+const RUNS: u32 = 20;
+
+fn time(runs: u32, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..runs {
+        f();
+    }
+    start.elapsed() / runs
+}
+
+fn main() {
+    let input = balanced_expr(DEPTH);
+
+    let boxed = time(RUNS, || {
+        let program = Parser::new(Lexer::new(&input)).parse_program();
+        assert_eq!(program.len(), 1);
+    });
+    let arena = time(RUNS, || {
+        let mut parser = ArenaParser::new(Lexer::new(&input));
+        assert!(parser.parse_expression().is_some());
+    });
+
+    println!("{} bytes, {} leaves", input.len(), 1usize << DEPTH);
+    println!("boxed: {:?} per parse", boxed);
+    println!("arena: {:?} per parse", arena);
+}
@@ -0,0 +1,306 @@
+use crate::ast::*;
+use crate::environment::Environment;
+use crate::evaluator::{eval_ident, eval_infix, eval_literal, eval_prefix};
+use crate::lexer::Lexer;
+use crate::object::Object;
+use crate::token::Token;
+
+/// Index of a node in an `ExprArena`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExprId(usize);
+
+/// Expression node whose children are arena indices instead of boxes
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    Ident(Ident),
+    Literal(Literal),
+    Prefix(Prefix, ExprId),
+    Infix(Infix, ExprId, ExprId),
+}
+
+/// Flat storage for expression nodes, so a whole expression takes one
+/// growing allocation rather than one box per child
+#[derive(Debug, Default)]
+pub struct ExprArena {
+    nodes: Vec<Node>,
+}
+
+impl ExprArena {
+    pub fn new() -> ExprArena {
+        ExprArena::default()
+    }
+
+    pub fn get(&self, id: ExprId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn alloc(&mut self, node: Node) -> ExprId {
+        self.nodes.push(node);
+        ExprId(self.nodes.len() - 1)
+    }
+}
+
+/// Evaluate an expression parsed by `ArenaParser`, with the same semantics
+/// as the boxed AST
+pub fn eval_arena(arena: &ExprArena, id: ExprId, env: &Environment) -> Object {
+    let eval = |_: &mut (), id: ExprId| eval_arena(arena, id, env);
+
+    match arena.get(id) {
+        Node::Ident(Ident(name)) => eval_ident(name, env),
+        Node::Literal(literal) => eval_literal(literal),
+        Node::Prefix(prefix, right) => eval_prefix(&mut (), prefix, *right, eval),
+        Node::Infix(infix, left, right) => eval_infix(&mut (), infix, *left, *right, eval),
+    }
+}
+
+/// Balanced expression with `2^depth` leaves, mixing every arithmetic
+/// operator, prefixes and the identifier `x`; used to compare the arena
+/// against the boxed AST
+pub fn balanced_expr(depth: usize) -> String {
+    fn build(depth: usize, leaf: &mut i64) -> String {
+        if depth == 0 {
+            *leaf += 1;
+            return match *leaf % 4 {
+                0 => String::from("x"),
+                1 => format!("{} * 2", leaf),
+                2 => format!("-{}", leaf),
+                _ => format!("{} / 3", leaf),
+            };
+        }
+
+        let op = if depth.is_multiple_of(2) { "+" } else { "-" };
+        let left = build(depth - 1, leaf);
+        let right = build(depth - 1, leaf);
+        format!("({} {} {})", left, op, right)
+    }
+
+    build(depth, &mut 0)
+}
+
+/// Alternate parser building a single expression into an `ExprArena`.
+/// Only identifiers, literals, prefix, infix and grouped expressions are
+/// supported; anything else is reported as an error.
+pub struct ArenaParser<'a> {
+    l: Lexer<'a>,
+    cur_token: Token,
+    peek_token: Token,
+    arena: ExprArena,
+    errors: Vec<String>,
+}
+
+impl<'a> ArenaParser<'a> {
+    pub fn new(l: Lexer<'a>) -> ArenaParser<'a> {
+        let mut p = ArenaParser {
+            l,
+            cur_token: Token::EOF,
+            peek_token: Token::EOF,
+            arena: ExprArena::new(),
+            errors: vec![],
+        };
+
+        p.next_token();
+        p.next_token();
+
+        p
+    }
+
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    pub fn arena(&self) -> &ExprArena {
+        &self.arena
+    }
+
+    fn next_token(&mut self) {
+        std::mem::swap(&mut self.cur_token, &mut self.peek_token);
+        self.peek_token = self.l.next_token();
+    }
+
+    /// Parse the whole input as one expression, with an optional trailing
+    /// semicolon
+    pub fn parse_expression(&mut self) -> Option<ExprId> {
+        let node = self.parse_expr(Precedence::Lowest)?;
+
+        if self.peek_token == Token::Semicolon {
+            self.next_token();
+        }
+        if self.peek_token != Token::EOF {
+            self.push_unexpected(self.peek_token.clone());
+            return None;
+        }
+
+        Some(self.arena.alloc(node))
+    }
+
+    /// Parse expression, leaving the returned node for the caller to
+    /// allocate
+    fn parse_expr(&mut self, precedence: Precedence) -> Option<Node> {
+        // prefix
+        let mut left = match self.cur_token {
+            Token::Ident(ref name) => Node::Ident(Ident(name.clone())),
+            Token::Int(int) => Node::Literal(Literal::Int(int)),
+            Token::Float(float) => Node::Literal(Literal::Float(float)),
+            Token::Str(ref string) => Node::Literal(Literal::String(string.clone())),
+            Token::True => Node::Literal(Literal::Bool(true)),
+            Token::False => Node::Literal(Literal::Bool(false)),
+            Token::Bang | Token::Plus | Token::Minus => self.parse_prefix_expr()?,
+            Token::Lparen => self.parse_grouped_expr()?,
+            _ => {
+                self.push_unexpected(self.cur_token.clone());
+                return None;
+            }
+        };
+
+        // infix
+        while precedence < Precedence::from_token(&self.peek_token) {
+            self.next_token();
+            let left_id = self.arena.alloc(left);
+            left = self.parse_infix_expr(left_id)?;
+        }
+
+        Some(left)
+    }
+
+    /// Parse prefix expression
+    fn parse_prefix_expr(&mut self) -> Option<Node> {
+        let prefix = match self.cur_token {
+            Token::Bang => Prefix::Bang,
+            Token::Plus => Prefix::Plus,
+            Token::Minus => Prefix::Minus,
+            _ => return None,
+        };
+
+        self.next_token();
+
+        let right = self.parse_expr(Precedence::Prefix)?;
+        Some(Node::Prefix(prefix, self.arena.alloc(right)))
+    }
+
+    /// Parse grouped expression
+    fn parse_grouped_expr(&mut self) -> Option<Node> {
+        self.next_token();
+
+        let node = self.parse_expr(Precedence::Lowest)?;
+
+        if self.peek_token != Token::Rparen {
+            self.push_unexpected(self.peek_token.clone());
+            return None;
+        }
+        self.next_token();
+
+        Some(node)
+    }
+
+    fn parse_infix_expr(&mut self, left: ExprId) -> Option<Node> {
+        let infix = match self.cur_token {
+            Token::Plus => Infix::Plus,
+            Token::Minus => Infix::Minus,
+            Token::Asterisk => Infix::Asterisk,
+            Token::Slash => Infix::Slash,
+            Token::Lt => Infix::Lt,
+            Token::Gt => Infix::Gt,
+            Token::Equal => Infix::Equal,
+            Token::NotEqual => Infix::NotEqual,
+            _ => {
+                self.push_unexpected(self.cur_token.clone());
+                return None;
+            }
+        };
+
+        let precedence = Precedence::from_token(&self.cur_token);
+
+        self.next_token();
+
+        let right = self.parse_expr(precedence)?;
+        Some(Node::Infix(infix, left, self.arena.alloc(right)))
+    }
+
+    fn push_unexpected(&mut self, tok: Token) {
+        self.errors
+            .push(format!("unexpected token {:?} in arena expression", tok));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::eval;
+    use crate::parser::{check_parser_errors, Parser};
+
+    #[test]
+    fn test_arena_matches_boxed() {
+        let large = balanced_expr(12);
+        let tests: Vec<String> = vec![
+            String::from("1 + 2 * 3"),
+            String::from("-(5 - 10) > !true"),
+            String::from("(1 + x) * (2 - x) / 3 == 4 != false"),
+            String::from("\"a\" == \"a\";"),
+            String::from("true + 1"),
+            format!("{} > 0", large),
+            large,
+        ];
+
+        for input in tests {
+            let mut env = Environment::new();
+            env.set(String::from("x"), Object::Integer(7));
+
+            let mut parser = Parser::new(Lexer::new(&input));
+            let program = parser.parse_program();
+            check_parser_errors(&parser);
+            let expected = eval(&program, &mut env);
+
+            let mut parser = ArenaParser::new(Lexer::new(&input));
+            let root = match parser.parse_expression() {
+                Some(root) => root,
+                None => panic!("{:?} failed to parse. errors={:?}", input, parser.errors()),
+            };
+            let result = eval_arena(parser.arena(), root, &env);
+
+            if result != expected {
+                panic!(
+                    "eval_arena({:?}) wrong. expected={:?}, got={:?}",
+                    input, expected, result
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_arena_parser() {
+        let mut parser = ArenaParser::new(Lexer::new("-a * (b + 1)"));
+        let root = parser.parse_expression().unwrap();
+        let arena = parser.arena();
+
+        let (left, right) = match arena.get(root) {
+            Node::Infix(Infix::Asterisk, left, right) => (*left, *right),
+            node => panic!("root wrong. got={:?}", node),
+        };
+        if !matches!(arena.get(left), Node::Prefix(Prefix::Minus, _)) {
+            panic!("left wrong. got={:?}", arena.get(left));
+        }
+        if !matches!(arena.get(right), Node::Infix(Infix::Plus, _, _)) {
+            panic!("right wrong. got={:?}", arena.get(right));
+        }
+        if arena.len() != 6 {
+            panic!("arena has not 6 nodes. got={}", arena.len());
+        }
+
+        let tests = ["f(x)", "1 +", "(1 + 2", "1 2"];
+
+        for input in tests {
+            let mut parser = ArenaParser::new(Lexer::new(input));
+            if parser.parse_expression().is_some() || parser.errors().is_empty() {
+                panic!("{:?} parsed without errors", input);
+            }
+        }
+    }
+}
@@ -1,14 +1,18 @@
-#[derive(Debug, PartialEq)]
+use std::collections::HashSet;
+
+use crate::token::Token;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Ident(pub String);
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Prefix {
     Bang,
     Plus,
     Minus,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Infix {
     Plus,
     Minus,
@@ -20,7 +24,7 @@ pub enum Infix {
     NotEqual,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Stmt {
     Blank,
     Let(Ident, Expr),
@@ -28,25 +32,74 @@ pub enum Stmt {
     Expr(Expr),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Expr {
     Ident(Ident),
     Literal(Literal),
     Prefix(Prefix, Box<Expr>),
     Infix(Infix, Box<Expr>, Box<Expr>),
+    Array(Vec<Expr>),
+    Hash(Vec<(Expr, Expr)>),
+    Function {
+        params: Vec<Ident>,
+        body: BlockStmt,
+    },
+    If {
+        condition: Box<Expr>,
+        consequence: BlockStmt,
+        alternative: Option<BlockStmt>,
+    },
+    Call {
+        function: Box<Expr>,
+        arguments: Vec<Expr>,
+    },
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+    },
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Literal {
     Int(i64),
+    Float(f64),
     String(String),
+    Bool(bool),
 }
 
 pub type BlockStmt = Vec<Stmt>;
 
 pub type Program = BlockStmt;
 
-#[derive(Debug, PartialEq)]
+impl Prefix {
+    /// Source spelling of the operator
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Prefix::Bang => "!",
+            Prefix::Plus => "+",
+            Prefix::Minus => "-",
+        }
+    }
+}
+
+impl Infix {
+    /// Source spelling of the operator
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Infix::Plus => "+",
+            Infix::Minus => "-",
+            Infix::Asterisk => "*",
+            Infix::Slash => "/",
+            Infix::Lt => "<",
+            Infix::Gt => ">",
+            Infix::Equal => "==",
+            Infix::NotEqual => "!=",
+        }
+    }
+}
+
+/// Operator binding power, ordered from loosest to tightest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Precedence {
     Lowest,
     Equals,      // ==
@@ -55,4 +108,728 @@ pub enum Precedence {
     Product,     // *
     Prefix,      // -X or !X
     Call,        // myFunction(X)
+    Index,       // array[index]
+}
+
+impl Precedence {
+    /// Binding power of the token as an infix operator
+    pub fn from_token(tok: &Token) -> Precedence {
+        match tok {
+            Token::Equal | Token::NotEqual => Precedence::Equals,
+            Token::Lt | Token::Gt => Precedence::LessGreater,
+            Token::Plus | Token::Minus => Precedence::Sum,
+            Token::Asterisk | Token::Slash => Precedence::Product,
+            Token::Lparen => Precedence::Call,
+            Token::Lbracket => Precedence::Index,
+            _ => Precedence::Lowest,
+        }
+    }
+}
+
+/// Render the program as Lisp-like s-expressions, one statement per line
+pub fn to_sexp(program: &Program) -> String {
+    program
+        .iter()
+        .filter(|stmt| **stmt != Stmt::Blank)
+        .map(stmt_to_sexp)
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn block_to_sexp(block: &[Stmt]) -> String {
+    let mut sexp = String::from("(do");
+    for stmt in block {
+        sexp.push(' ');
+        sexp.push_str(&stmt_to_sexp(stmt));
+    }
+    sexp.push(')');
+    sexp
+}
+
+/// Describe each statement index where the two programs differ, rendering
+/// the statements as s-expressions
+pub fn program_diff(a: &Program, b: &Program) -> Vec<String> {
+    let mut diffs = vec![];
+
+    for i in 0..a.len().max(b.len()) {
+        match (a.get(i), b.get(i)) {
+            (Some(left), Some(right)) if left != right => diffs.push(format!(
+                "statement {}: {} != {}",
+                i,
+                stmt_to_sexp(left),
+                stmt_to_sexp(right)
+            )),
+            (Some(left), None) => diffs.push(format!(
+                "statement {}: only in left: {}",
+                i,
+                stmt_to_sexp(left)
+            )),
+            (None, Some(right)) => diffs.push(format!(
+                "statement {}: only in right: {}",
+                i,
+                stmt_to_sexp(right)
+            )),
+            _ => {}
+        }
+    }
+
+    diffs
+}
+
+fn stmt_to_sexp(stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::Blank => String::new(),
+        Stmt::Let(Ident(name), expr) => format!("(let {} {})", name, expr_to_sexp(expr)),
+        Stmt::Return(expr) => format!("(return {})", expr_to_sexp(expr)),
+        Stmt::Expr(expr) => expr_to_sexp(expr),
+    }
+}
+
+fn expr_to_sexp(expr: &Expr) -> String {
+    match expr {
+        Expr::Ident(Ident(name)) => name.clone(),
+        Expr::Literal(Literal::Int(int)) => int.to_string(),
+        Expr::Literal(Literal::Float(float)) => format!("{:?}", float),
+        Expr::Literal(Literal::String(string)) => format!("{:?}", string),
+        Expr::Literal(Literal::Bool(bool)) => bool.to_string(),
+        Expr::Prefix(prefix, right) => format!("({} {})", prefix.symbol(), expr_to_sexp(right)),
+        Expr::Infix(infix, left, right) => format!(
+            "({} {} {})",
+            infix.symbol(),
+            expr_to_sexp(left),
+            expr_to_sexp(right)
+        ),
+        Expr::Array(elements) => {
+            let mut sexp = String::from("(array");
+            for element in elements {
+                sexp.push(' ');
+                sexp.push_str(&expr_to_sexp(element));
+            }
+            sexp.push(')');
+            sexp
+        }
+        Expr::Function { params, body } => {
+            let params: Vec<&str> = params.iter().map(|Ident(name)| name.as_str()).collect();
+            let mut sexp = format!("(fn ({})", params.join(" "));
+            for stmt in body {
+                sexp.push(' ');
+                sexp.push_str(&stmt_to_sexp(stmt));
+            }
+            sexp.push(')');
+            sexp
+        }
+        Expr::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            let mut sexp = format!(
+                "(if {} {}",
+                expr_to_sexp(condition),
+                block_to_sexp(consequence)
+            );
+            if let Some(alternative) = alternative {
+                sexp.push(' ');
+                sexp.push_str(&block_to_sexp(alternative));
+            }
+            sexp.push(')');
+            sexp
+        }
+        Expr::Call {
+            function,
+            arguments,
+        } => {
+            let mut sexp = format!("(call {}", expr_to_sexp(function));
+            for argument in arguments {
+                sexp.push(' ');
+                sexp.push_str(&expr_to_sexp(argument));
+            }
+            sexp.push(')');
+            sexp
+        }
+        Expr::Index { object, index } => {
+            format!("(index {} {})", expr_to_sexp(object), expr_to_sexp(index))
+        }
+        Expr::Hash(pairs) => {
+            let mut sexp = String::from("(hash");
+            for (key, value) in pairs {
+                sexp.push_str(&format!(" ({} {})", expr_to_sexp(key), expr_to_sexp(value)));
+            }
+            sexp.push(')');
+            sexp
+        }
+    }
+}
+
+/// Identifiers referenced by the expression but not bound within it
+pub fn free_variables(expr: &Expr) -> HashSet<String> {
+    let mut free = HashSet::new();
+    collect_free_variables(expr, &HashSet::new(), &mut free);
+    free
+}
+
+fn collect_free_variables(expr: &Expr, bound: &HashSet<String>, free: &mut HashSet<String>) {
+    match expr {
+        Expr::Ident(Ident(name)) => {
+            if !bound.contains(name) {
+                free.insert(name.clone());
+            }
+        }
+        Expr::Literal(_) => {}
+        Expr::Prefix(_, right) => collect_free_variables(right, bound, free),
+        Expr::Infix(_, left, right)
+        | Expr::Index {
+            object: left,
+            index: right,
+        } => {
+            collect_free_variables(left, bound, free);
+            collect_free_variables(right, bound, free);
+        }
+        Expr::Array(elements) => {
+            for element in elements {
+                collect_free_variables(element, bound, free);
+            }
+        }
+        Expr::Hash(pairs) => {
+            for (key, value) in pairs {
+                collect_free_variables(key, bound, free);
+                collect_free_variables(value, bound, free);
+            }
+        }
+        Expr::Function { params, body } => {
+            let mut bound = bound.clone();
+            bound.extend(params.iter().map(|Ident(name)| name.clone()));
+            collect_free_variables_in_block(body, &mut bound, free);
+        }
+        Expr::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            collect_free_variables(condition, bound, free);
+            collect_free_variables_in_block(consequence, &mut bound.clone(), free);
+            if let Some(alternative) = alternative {
+                collect_free_variables_in_block(alternative, &mut bound.clone(), free);
+            }
+        }
+        Expr::Call {
+            function,
+            arguments,
+        } => {
+            collect_free_variables(function, bound, free);
+            for argument in arguments {
+                collect_free_variables(argument, bound, free);
+            }
+        }
+    }
+}
+
+/// A `let` binds its name for the statements after it in the block
+fn collect_free_variables_in_block(
+    block: &[Stmt],
+    bound: &mut HashSet<String>,
+    free: &mut HashSet<String>,
+) {
+    for stmt in block {
+        match stmt {
+            Stmt::Blank => {}
+            Stmt::Let(Ident(name), expr) => {
+                collect_free_variables(expr, bound, free);
+                bound.insert(name.clone());
+            }
+            Stmt::Return(expr) | Stmt::Expr(expr) => collect_free_variables(expr, bound, free),
+        }
+    }
+}
+
+/// Names introduced by `let` statements and function parameters, in source
+/// order; shadowing declarations appear once per declaration
+pub fn declared_idents(program: &Program) -> Vec<String> {
+    let mut idents = vec![];
+    collect_declared_in_block(program, &mut idents);
+    idents
+}
+
+fn collect_declared_in_block(block: &[Stmt], idents: &mut Vec<String>) {
+    for stmt in block {
+        match stmt {
+            Stmt::Blank => {}
+            Stmt::Let(Ident(name), expr) => {
+                idents.push(name.clone());
+                collect_declared(expr, idents);
+            }
+            Stmt::Return(expr) | Stmt::Expr(expr) => collect_declared(expr, idents),
+        }
+    }
+}
+
+fn collect_declared(expr: &Expr, idents: &mut Vec<String>) {
+    match expr {
+        Expr::Ident(_) | Expr::Literal(_) => {}
+        Expr::Prefix(_, right) => collect_declared(right, idents),
+        Expr::Infix(_, left, right)
+        | Expr::Index {
+            object: left,
+            index: right,
+        } => {
+            collect_declared(left, idents);
+            collect_declared(right, idents);
+        }
+        Expr::Array(elements) => {
+            for element in elements {
+                collect_declared(element, idents);
+            }
+        }
+        Expr::Hash(pairs) => {
+            for (key, value) in pairs {
+                collect_declared(key, idents);
+                collect_declared(value, idents);
+            }
+        }
+        Expr::Function { params, body } => {
+            idents.extend(params.iter().map(|Ident(name)| name.clone()));
+            collect_declared_in_block(body, idents);
+        }
+        Expr::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            collect_declared(condition, idents);
+            collect_declared_in_block(consequence, idents);
+            if let Some(alternative) = alternative {
+                collect_declared_in_block(alternative, idents);
+            }
+        }
+        Expr::Call {
+            function,
+            arguments,
+        } => {
+            collect_declared(function, idents);
+            for argument in arguments {
+                collect_declared(argument, idents);
+            }
+        }
+    }
+}
+
+/// Deepest nesting of expressions in the program; a lone literal is 1
+pub fn max_nesting_depth(program: &Program) -> usize {
+    block_depth(program)
+}
+
+fn block_depth(block: &[Stmt]) -> usize {
+    block.iter().map(stmt_depth).max().unwrap_or(0)
+}
+
+fn stmt_depth(stmt: &Stmt) -> usize {
+    match stmt {
+        Stmt::Blank => 0,
+        Stmt::Let(_, expr) | Stmt::Return(expr) | Stmt::Expr(expr) => expr_depth(expr),
+    }
+}
+
+fn expr_depth(expr: &Expr) -> usize {
+    match expr {
+        Expr::Ident(_) | Expr::Literal(_) => 1,
+        Expr::Prefix(_, right) => 1 + expr_depth(right),
+        Expr::Infix(_, left, right)
+        | Expr::Index {
+            object: left,
+            index: right,
+        } => 1 + expr_depth(left).max(expr_depth(right)),
+        Expr::Array(elements) => 1 + elements.iter().map(expr_depth).max().unwrap_or(0),
+        Expr::Function { body, .. } => 1 + block_depth(body),
+        Expr::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            let alternative = alternative.as_ref().map_or(0, |block| block_depth(block));
+            1 + expr_depth(condition)
+                .max(block_depth(consequence))
+                .max(alternative)
+        }
+        Expr::Call {
+            function,
+            arguments,
+        } => {
+            let arguments = arguments.iter().map(expr_depth).max().unwrap_or(0);
+            1 + expr_depth(function).max(arguments)
+        }
+        Expr::Hash(pairs) => {
+            let depth = |(key, value): &(Expr, Expr)| expr_depth(key).max(expr_depth(value));
+            1 + pairs.iter().map(depth).max().unwrap_or(0)
+        }
+    }
+}
+
+/// Rewrite the program into a canonical form so that equivalent programs
+/// compare equal. The rewrites, applied bottom-up, are:
+///
+/// - integer `+ - * /` and `< > == !=` on two literals fold to a literal,
+///   unless the operation overflows or divides by zero
+/// - `==` and `!=` on two boolean literals fold to a literal
+/// - `x + 0`, `0 + x`, `x - 0`, `x * 1`, `1 * x` and `x / 1` become `x`,
+///   unless `x` is certainly not an integer (e.g. `"a" * 1` or `!y + 0`,
+///   which fail when evaluated). These rewrites assume identifiers, calls
+///   and other expressions of unknown type evaluate to integers; where they
+///   do not, canonicalization can turn a type error into a value.
+/// - `-` and `+` on an integer literal fold into the literal
+/// - `!` on a boolean literal folds into the literal
+pub fn canonicalize(program: Program) -> Program {
+    program.into_iter().map(canonicalize_stmt).collect()
+}
+
+fn canonicalize_stmt(stmt: Stmt) -> Stmt {
+    match stmt {
+        Stmt::Blank => Stmt::Blank,
+        Stmt::Let(ident, expr) => Stmt::Let(ident, canonicalize_expr(expr)),
+        Stmt::Return(expr) => Stmt::Return(canonicalize_expr(expr)),
+        Stmt::Expr(expr) => Stmt::Expr(canonicalize_expr(expr)),
+    }
+}
+
+fn canonicalize_expr(expr: Expr) -> Expr {
+    match expr {
+        Expr::Prefix(prefix, right) => canonicalize_prefix(prefix, canonicalize_expr(*right)),
+        Expr::Infix(infix, left, right) => {
+            canonicalize_infix(infix, canonicalize_expr(*left), canonicalize_expr(*right))
+        }
+        Expr::Array(elements) => Expr::Array(elements.into_iter().map(canonicalize_expr).collect()),
+        Expr::Function { params, body } => Expr::Function {
+            params,
+            body: canonicalize(body),
+        },
+        Expr::If {
+            condition,
+            consequence,
+            alternative,
+        } => Expr::If {
+            condition: Box::new(canonicalize_expr(*condition)),
+            consequence: canonicalize(consequence),
+            alternative: alternative.map(canonicalize),
+        },
+        Expr::Call {
+            function,
+            arguments,
+        } => Expr::Call {
+            function: Box::new(canonicalize_expr(*function)),
+            arguments: arguments.into_iter().map(canonicalize_expr).collect(),
+        },
+        Expr::Index { object, index } => Expr::Index {
+            object: Box::new(canonicalize_expr(*object)),
+            index: Box::new(canonicalize_expr(*index)),
+        },
+        Expr::Hash(pairs) => Expr::Hash(
+            pairs
+                .into_iter()
+                .map(|(key, value)| (canonicalize_expr(key), canonicalize_expr(value)))
+                .collect(),
+        ),
+        expr => expr,
+    }
+}
+
+fn canonicalize_prefix(prefix: Prefix, right: Expr) -> Expr {
+    match (prefix, right) {
+        (Prefix::Plus, Expr::Literal(Literal::Int(int))) => Expr::Literal(Literal::Int(int)),
+        (Prefix::Minus, Expr::Literal(Literal::Int(int))) if int != i64::MIN => {
+            Expr::Literal(Literal::Int(-int))
+        }
+        (Prefix::Bang, Expr::Literal(Literal::Bool(bool))) => Expr::Literal(Literal::Bool(!bool)),
+        (prefix, right) => Expr::Prefix(prefix, Box::new(right)),
+    }
+}
+
+fn canonicalize_infix(infix: Infix, left: Expr, right: Expr) -> Expr {
+    match (&left, &right) {
+        (Expr::Literal(Literal::Int(l)), Expr::Literal(Literal::Int(r))) => {
+            if let Some(literal) = fold_int_infix(&infix, *l, *r) {
+                return Expr::Literal(literal);
+            }
+        }
+        (Expr::Literal(Literal::Bool(l)), Expr::Literal(Literal::Bool(r))) => match infix {
+            Infix::Equal => return Expr::Literal(Literal::Bool(l == r)),
+            Infix::NotEqual => return Expr::Literal(Literal::Bool(l != r)),
+            _ => {}
+        },
+        _ => {}
+    }
+
+    let is_int = |expr: &Expr, value: i64| *expr == Expr::Literal(Literal::Int(value));
+
+    match infix {
+        Infix::Plus | Infix::Minus if is_int(&right, 0) && may_be_int(&left) => left,
+        Infix::Asterisk | Infix::Slash if is_int(&right, 1) && may_be_int(&left) => left,
+        Infix::Plus if is_int(&left, 0) && may_be_int(&right) => right,
+        Infix::Asterisk if is_int(&left, 1) && may_be_int(&right) => right,
+        infix => Expr::Infix(infix, Box::new(left), Box::new(right)),
+    }
+}
+
+/// Whether the expression could evaluate to an integer
+fn may_be_int(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(literal) => matches!(literal, Literal::Int(_)),
+        Expr::Prefix(prefix, _) => *prefix != Prefix::Bang,
+        Expr::Infix(infix, _, _) => matches!(
+            infix,
+            Infix::Plus | Infix::Minus | Infix::Asterisk | Infix::Slash
+        ),
+        Expr::Array(_) | Expr::Hash(_) | Expr::Function { .. } => false,
+        Expr::Ident(_) | Expr::If { .. } | Expr::Call { .. } | Expr::Index { .. } => true,
+    }
+}
+
+fn fold_int_infix(infix: &Infix, left: i64, right: i64) -> Option<Literal> {
+    match infix {
+        Infix::Plus => left.checked_add(right).map(Literal::Int),
+        Infix::Minus => left.checked_sub(right).map(Literal::Int),
+        Infix::Asterisk => left.checked_mul(right).map(Literal::Int),
+        Infix::Slash => left.checked_div(right).map(Literal::Int),
+        Infix::Lt => Some(Literal::Bool(left < right)),
+        Infix::Gt => Some(Literal::Bool(left > right)),
+        Infix::Equal => Some(Literal::Bool(left == right)),
+        Infix::NotEqual => Some(Literal::Bool(left != right)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_to_sexp() {
+        let tests: Vec<(&str, &str)> = vec![
+            ("let x = 1 + 2;", "(let x (+ 1 2))"),
+            ("-a * b", "(* (- a) b)"),
+            (
+                "return [1, \"a\", [true]];",
+                "(return (array 1 \"a\" (array true)))",
+            ),
+            ("{\"k\": x + 1}", "(hash (\"k\" (+ x 1)))"),
+            (
+                "fn(x, y) { let z = x; z * y }",
+                "(fn (x y) (let z x) (* z y))",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input)).parse_program();
+            let sexp = to_sexp(&program);
+            if sexp != expected {
+                panic!(
+                    "to_sexp({:?}) wrong. expected={}, got={}",
+                    input, expected, sexp
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_free_variables() {
+        let tests: Vec<(&str, Vec<&str>)> = vec![
+            ("5", vec![]),
+            ("x", vec!["x"]),
+            ("-x + y * x", vec!["x", "y"]),
+            ("fn(x) { x + y }", vec!["y"]),
+            ("fn(x) { let z = x; z + w }", vec!["w"]),
+            ("fn(x) { fn(y) { x + y + z } }", vec!["z"]),
+            ("if (a) { let b = 1; b } else { b }", vec!["a", "b"]),
+            ("fn(x) { f(x, y) }", vec!["f", "y"]),
+        ];
+
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input)).parse_program();
+            let free = match program.first() {
+                Some(Stmt::Expr(expr)) => free_variables(expr),
+                stmt => panic!("not an expression statement. got={:?}", stmt),
+            };
+            let expected: HashSet<String> = expected.iter().map(|s| s.to_string()).collect();
+
+            if free != expected {
+                panic!(
+                    "free_variables({:?}) wrong. expected={:?}, got={:?}",
+                    input, expected, free
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_program_diff() {
+        let tests: Vec<(&str, &str, Vec<&str>)> = vec![
+            ("let x = 1; x + 2;", "let x = 1; x + 2;", vec![]),
+            (
+                "let x = 1; x + 2; y;",
+                "let x = 1; x * 2; y;",
+                vec!["statement 1: (+ x 2) != (* x 2)"],
+            ),
+            ("x; y;", "x;", vec!["statement 1: only in left: y"]),
+            (
+                "x;",
+                "x; return y;",
+                vec!["statement 1: only in right: (return y)"],
+            ),
+        ];
+
+        for (a, b, expected) in tests {
+            let left = Parser::new(Lexer::new(a)).parse_program();
+            let right = Parser::new(Lexer::new(b)).parse_program();
+            let diffs = program_diff(&left, &right);
+
+            if diffs != expected {
+                panic!(
+                    "program_diff({:?}, {:?}) wrong. expected={:?}, got={:?}",
+                    a, b, expected, diffs
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_nesting_depth() {
+        let tests: Vec<(&str, usize)> = vec![
+            ("", 0),
+            ("let x = 1; y; return 2;", 1),
+            ("((1));", 1),
+            ("-x;", 2),
+            ("1 * 2 + 3; -a;", 3),
+            ("fn(x) { x + 1 }", 3),
+            ("fn(x) { if (x) { x + 1 } }", 4),
+        ];
+
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input)).parse_program();
+            let depth = max_nesting_depth(&program);
+
+            if depth != expected {
+                panic!(
+                    "max_nesting_depth({:?}) wrong. expected={}, got={}",
+                    input, expected, depth
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let tests: Vec<(&str, &str)> = vec![
+            ("x + 0;", "x;"),
+            ("0 + x;", "x;"),
+            ("x * 1 - 0;", "x;"),
+            ("(1 + 2) * 3;", "9;"),
+            ("-5;", "0 - 5;"),
+            ("!true;", "false;"),
+            ("(1 < 2) == true;", "true;"),
+            ("let y = x * (2 - 1);", "let y = x;"),
+            ("x + 1;", "x + 1;"),
+            ("1 / 0;", "1 / 0;"),
+            ("f(x) * 1;", "f(x);"),
+        ];
+
+        for (input, expected) in tests {
+            let program = canonicalize(Parser::new(Lexer::new(input)).parse_program());
+            let expected = canonicalize(Parser::new(Lexer::new(expected)).parse_program());
+
+            if program != expected {
+                panic!(
+                    "canonicalize({:?}) wrong. expected={:?}, got={:?}",
+                    input, expected, program
+                );
+            }
+        }
+
+        // Identity rewrites must not hide a type error
+        let unchanged = [
+            "\"a\" * 1;",
+            "true + 0;",
+            "0 + (1 < x);",
+            "[1] * 1;",
+            "1 * !x;",
+        ];
+
+        for input in unchanged {
+            let program = Parser::new(Lexer::new(input)).parse_program();
+            let expected = Parser::new(Lexer::new(input)).parse_program();
+
+            let program = canonicalize(program);
+            if program != expected {
+                panic!(
+                    "canonicalize({:?}) wrong. expected={:?}, got={:?}",
+                    input, expected, program
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_precedence_order() {
+        let levels = [
+            Precedence::Lowest,
+            Precedence::Equals,
+            Precedence::LessGreater,
+            Precedence::Sum,
+            Precedence::Product,
+            Precedence::Prefix,
+            Precedence::Call,
+            Precedence::Index,
+        ];
+
+        for pair in levels.windows(2) {
+            if pair[0] >= pair[1] {
+                panic!("{:?} should bind looser than {:?}", pair[0], pair[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_precedence_from_token() {
+        let tests: Vec<(Token, Precedence)> = vec![
+            (Token::Equal, Precedence::Equals),
+            (Token::NotEqual, Precedence::Equals),
+            (Token::Lt, Precedence::LessGreater),
+            (Token::Gt, Precedence::LessGreater),
+            (Token::Plus, Precedence::Sum),
+            (Token::Minus, Precedence::Sum),
+            (Token::Asterisk, Precedence::Product),
+            (Token::Slash, Precedence::Product),
+            (Token::Lparen, Precedence::Call),
+            (Token::Lbracket, Precedence::Index),
+            (Token::Semicolon, Precedence::Lowest),
+            (Token::Int(1), Precedence::Lowest),
+        ];
+
+        for (tok, expected) in tests {
+            let precedence = Precedence::from_token(&tok);
+            if precedence != expected {
+                panic!(
+                    "from_token({:?}) wrong. expected={:?}, got={:?}",
+                    tok, expected, precedence
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_declared_idents() {
+        let input = "
+let x = 1;
+let add = fn(a, b) {
+    let sum = a + b;
+    if (sum > x) { let x = sum; x } else { sum }
+};
+let x = x * 2;
+";
+        let program = Parser::new(Lexer::new(input)).parse_program();
+        let idents = declared_idents(&program);
+        let expected = vec!["x", "add", "a", "b", "sum", "x", "x"];
+
+        if idents != expected {
+            panic!("got={:?}. expected={:?}", idents, expected);
+        }
+    }
 }
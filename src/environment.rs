@@ -0,0 +1,75 @@
+use crate::object::Object;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Environment {
+    store: HashMap<String, Object>,
+    outer: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    /// Scope nested in `outer`, e.g. for a function call
+    pub fn new_enclosed(outer: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            store: HashMap::new(),
+            outer: Some(outer),
+        }
+    }
+
+    /// Look up `name` here, then in the enclosing scopes
+    pub fn get(&self, name: &str) -> Option<Object> {
+        match self.store.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self
+                .outer
+                .as_ref()
+                .and_then(|outer| outer.borrow().get(name)),
+        }
+    }
+
+    /// Bind `name` in this scope only, shadowing any outer binding
+    pub fn set(&mut self, name: String, val: Object) {
+        self.store.insert(name, val);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enclosed_environment() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer
+            .borrow_mut()
+            .set(String::from("x"), Object::Integer(5));
+        outer
+            .borrow_mut()
+            .set(String::from("y"), Object::Integer(7));
+
+        let mut inner = Environment::new_enclosed(Rc::clone(&outer));
+        inner.set(String::from("x"), Object::Integer(10));
+
+        let tests: Vec<(Option<Object>, Option<Object>)> = vec![
+            (inner.get("x"), Some(Object::Integer(10))),
+            (inner.get("y"), Some(Object::Integer(7))),
+            (outer.borrow().get("x"), Some(Object::Integer(5))),
+            (inner.get("z"), None),
+        ];
+
+        for (i, (got, expected)) in tests.into_iter().enumerate() {
+            if got != expected {
+                panic!(
+                    "tests[{}] - wrong value. expected={:?}, got={:?}",
+                    i, expected, got
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,492 @@
+use crate::ast::*;
+use crate::environment::Environment;
+use crate::object::Object;
+
+/// Evaluate program, unwrapping a top-level return and stopping at the
+/// first error
+pub fn eval(program: &Program, env: &mut Environment) -> Object {
+    match eval_block_stmt(program, env) {
+        Object::ReturnValue(value) => *value,
+        obj => obj,
+    }
+}
+
+/// Evaluate statements in order, yielding the last value. A return value
+/// or error stops evaluation and is passed up as is, so a `return` inside
+/// nested blocks reaches the enclosing function or program.
+fn eval_block_stmt(stmts: &[Stmt], env: &mut Environment) -> Object {
+    let mut result = Object::Null;
+
+    for stmt in stmts {
+        match eval_stmt(stmt, env) {
+            obj @ Object::ReturnValue(_) | obj @ Object::Error(_) => return obj,
+            obj => result = obj,
+        }
+    }
+
+    result
+}
+
+fn eval_stmt(stmt: &Stmt, env: &mut Environment) -> Object {
+    match stmt {
+        Stmt::Blank => Object::Null,
+        Stmt::Let(ident, expr) => eval_let_stmt(ident, expr, env),
+        Stmt::Return(expr) => match eval_expr(expr, env) {
+            obj @ Object::Error(_) => obj,
+            obj => Object::ReturnValue(Box::new(obj)),
+        },
+        Stmt::Expr(expr) => eval_expr(expr, env),
+    }
+}
+
+/// Bind the evaluated value in the current scope. The value is evaluated
+/// before binding, so `let x = x + 1` reads any earlier `x`.
+fn eval_let_stmt(ident: &Ident, expr: &Expr, env: &mut Environment) -> Object {
+    let value = eval_expr(expr, env);
+    if let Object::Error(_) = value {
+        return value;
+    }
+
+    let Ident(name) = ident;
+    env.set(name.clone(), value);
+    Object::Null
+}
+
+fn eval_expr(expr: &Expr, env: &mut Environment) -> Object {
+    let eval = |env: &mut Environment, expr: &Expr| eval_expr(expr, env);
+
+    match expr {
+        Expr::Ident(Ident(name)) => eval_ident(name, env),
+        Expr::Literal(literal) => eval_literal(literal),
+        Expr::Prefix(prefix, right) => eval_prefix(env, prefix, &**right, eval),
+        Expr::Infix(infix, left, right) => eval_infix(env, infix, &**left, &**right, eval),
+        Expr::If {
+            condition,
+            consequence,
+            alternative,
+        } => eval_if_expr(condition, consequence, alternative, env),
+        _ => Object::Error(String::from("unsupported expression")),
+    }
+}
+
+pub(crate) fn eval_ident(name: &str, env: &Environment) -> Object {
+    match env.get(name) {
+        Some(value) => value,
+        None => Object::Error(format!("identifier not found: {}", name)),
+    }
+}
+
+pub(crate) fn eval_literal(literal: &Literal) -> Object {
+    match literal {
+        Literal::Int(value) => Object::Integer(*value),
+        Literal::Bool(value) => Object::Boolean(*value),
+        Literal::String(value) => Object::StringVal(value.clone()),
+        Literal::Float(_) => Object::Error(String::from("unsupported expression")),
+    }
+}
+
+/// Evaluate the operand with `eval`, stopping at an error, then apply the
+/// operator. Generic over the operand so every expression representation
+/// shares the same rules.
+pub(crate) fn eval_prefix<C, T>(
+    ctx: &mut C,
+    prefix: &Prefix,
+    right: T,
+    eval: impl Fn(&mut C, T) -> Object,
+) -> Object {
+    let right = eval(ctx, right);
+    if let Object::Error(_) = right {
+        return right;
+    }
+    eval_prefix_expr(prefix, right)
+}
+
+/// Evaluate both operands in order with `eval`, stopping at the first
+/// error, then apply the operator
+pub(crate) fn eval_infix<C, T>(
+    ctx: &mut C,
+    infix: &Infix,
+    left: T,
+    right: T,
+    eval: impl Fn(&mut C, T) -> Object,
+) -> Object {
+    let left = eval(ctx, left);
+    if let Object::Error(_) = left {
+        return left;
+    }
+    let right = eval(ctx, right);
+    if let Object::Error(_) = right {
+        return right;
+    }
+    eval_infix_expr(infix, left, right)
+}
+
+/// Evaluate the branch picked by the condition's truthiness; a false
+/// condition without an `else` yields `null`
+fn eval_if_expr(
+    condition: &Expr,
+    consequence: &[Stmt],
+    alternative: &Option<BlockStmt>,
+    env: &mut Environment,
+) -> Object {
+    let condition = eval_expr(condition, env);
+    if let Object::Error(_) = condition {
+        return condition;
+    }
+
+    if condition.is_truthy() {
+        eval_block_stmt(consequence, env)
+    } else if let Some(alternative) = alternative {
+        eval_block_stmt(alternative, env)
+    } else {
+        Object::Null
+    }
+}
+
+/// `!` negates by truthiness: `false` and `null` are falsy, every other
+/// value is truthy. `-` and `+` only apply to integers.
+fn eval_prefix_expr(prefix: &Prefix, right: Object) -> Object {
+    match (prefix, &right) {
+        (Prefix::Bang, _) => Object::Boolean(!right.is_truthy()),
+        (Prefix::Minus, Object::Integer(value)) => match value.checked_neg() {
+            Some(value) => Object::Integer(value),
+            None => Object::Error(String::from("integer overflow")),
+        },
+        (Prefix::Plus, Object::Integer(value)) => Object::Integer(*value),
+        _ => Object::Error(format!(
+            "unknown operator: {}{}",
+            prefix.symbol(),
+            right.type_name()
+        )),
+    }
+}
+
+fn eval_infix_expr(infix: &Infix, left: Object, right: Object) -> Object {
+    match (&left, &right) {
+        (Object::Integer(l), Object::Integer(r)) => eval_int_infix_expr(infix, *l, *r),
+        (Object::Boolean(l), Object::Boolean(r)) => match infix {
+            Infix::Equal => Object::Boolean(l == r),
+            Infix::NotEqual => Object::Boolean(l != r),
+            _ => infix_error("unknown operator", infix, &left, &right),
+        },
+        (Object::StringVal(l), Object::StringVal(r)) => match infix {
+            Infix::Equal => Object::Boolean(l == r),
+            Infix::NotEqual => Object::Boolean(l != r),
+            _ => infix_error("unknown operator", infix, &left, &right),
+        },
+        _ if left.type_name() != right.type_name() => {
+            infix_error("type mismatch", infix, &left, &right)
+        }
+        _ => infix_error("unknown operator", infix, &left, &right),
+    }
+}
+
+fn eval_int_infix_expr(infix: &Infix, left: i64, right: i64) -> Object {
+    let value = match infix {
+        Infix::Plus => left.checked_add(right),
+        Infix::Minus => left.checked_sub(right),
+        Infix::Asterisk => left.checked_mul(right),
+        Infix::Slash if right == 0 => return Object::Error(String::from("division by zero")),
+        Infix::Slash => left.checked_div(right),
+        Infix::Lt => return Object::Boolean(left < right),
+        Infix::Gt => return Object::Boolean(left > right),
+        Infix::Equal => return Object::Boolean(left == right),
+        Infix::NotEqual => return Object::Boolean(left != right),
+    };
+
+    match value {
+        Some(value) => Object::Integer(value),
+        None => Object::Error(String::from("integer overflow")),
+    }
+}
+
+fn infix_error(message: &str, infix: &Infix, left: &Object, right: &Object) -> Object {
+    Object::Error(format!(
+        "{}: {} {} {}",
+        message,
+        left.type_name(),
+        infix.symbol(),
+        right.type_name()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_eval() {
+        let program = Parser::new(Lexer::new("let x = 5; x + 3")).parse_program();
+        let result = eval(&program, &mut Environment::new());
+
+        if result != Object::Integer(8) {
+            panic!("got={:?}. expected={:?}", result, Object::Integer(8));
+        }
+    }
+
+    #[test]
+    fn test_eval_let_stmt() {
+        let tests: Vec<(&str, Object)> = vec![
+            ("let x = 5; let y = x + 3; y", Object::Integer(8)),
+            ("let x = 1; let x = 2; x", Object::Integer(2)),
+            ("let x = 1; let x = x + 1; x", Object::Integer(2)),
+            ("let x = 5;", Object::Null),
+            (
+                "let x = x;",
+                Object::Error(String::from("identifier not found: x")),
+            ),
+            ("y", Object::Error(String::from("identifier not found: y"))),
+        ];
+
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input)).parse_program();
+            let result = eval(&program, &mut Environment::new());
+            if result != expected {
+                panic!(
+                    "eval({:?}) wrong. expected={:?}, got={:?}",
+                    input, expected, result
+                );
+            }
+        }
+
+        let mut env = Environment::new();
+        let program = Parser::new(Lexer::new("let x = 1 + 2")).parse_program();
+        eval(&program, &mut env);
+        if env.get("x") != Some(Object::Integer(3)) {
+            panic!("x wrong. expected=Integer(3), got={:?}", env.get("x"));
+        }
+
+        let program = Parser::new(Lexer::new("let y = x * 2")).parse_program();
+        eval(&program, &mut env);
+        if env.get("y") != Some(Object::Integer(6)) {
+            panic!("y wrong. expected=Integer(6), got={:?}", env.get("y"));
+        }
+    }
+
+    #[test]
+    fn test_eval_block_stmt() {
+        let tests: Vec<(&str, Object, Option<i64>)> = vec![
+            ("let x = 2; x * 3", Object::Integer(6), None),
+            (
+                "let x = 1; return x + 1; let y = 9;",
+                Object::ReturnValue(Box::new(Object::Integer(2))),
+                None,
+            ),
+            (
+                "let y = 1; let x = true + 1; let y = 2;",
+                Object::Error(String::from("type mismatch: BOOLEAN + INTEGER")),
+                Some(1),
+            ),
+            ("", Object::Null, None),
+        ];
+
+        for (input, expected, y) in tests {
+            let program = Parser::new(Lexer::new(input)).parse_program();
+            let mut env = Environment::new();
+            let result = eval_block_stmt(&program, &mut env);
+            if result != expected {
+                panic!(
+                    "eval_block_stmt({:?}) wrong. expected={:?}, got={:?}",
+                    input, expected, result
+                );
+            }
+
+            let y = y.map(Object::Integer);
+            if env.get("y") != y {
+                panic!(
+                    "eval_block_stmt({:?}) - y wrong. expected={:?}, got={:?}",
+                    input,
+                    y,
+                    env.get("y")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval_if_expr() {
+        let tests: Vec<(&str, Object)> = vec![
+            ("if (true) { 10 }", Object::Integer(10)),
+            ("if (false) { 10 }", Object::Null),
+            ("if (1) { 10 }", Object::Integer(10)),
+            ("if (1 < 2) { 10 } else { 20 }", Object::Integer(10)),
+            ("if (1 > 2) { 10 } else { 20 }", Object::Integer(20)),
+            (
+                "if (1 > 2) { if (true) { 10 } } else { 20 }",
+                Object::Integer(20),
+            ),
+            (
+                "if (1 > 2) { 10 } else if (true) { 30 }",
+                Object::Integer(30),
+            ),
+            ("if (true) { if (false) { 10 } }", Object::Null),
+            (
+                "if (true + 1) { 10 }",
+                Object::Error(String::from("type mismatch: BOOLEAN + INTEGER")),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input)).parse_program();
+            let result = eval(&program, &mut Environment::new());
+            if result != expected {
+                panic!(
+                    "eval({:?}) wrong. expected={:?}, got={:?}",
+                    input, expected, result
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval_prefix_expr() {
+        let tests: Vec<(Prefix, Object, Object)> = vec![
+            (Prefix::Bang, Object::Boolean(true), Object::Boolean(false)),
+            (Prefix::Bang, Object::Boolean(false), Object::Boolean(true)),
+            (Prefix::Bang, Object::Null, Object::Boolean(true)),
+            (Prefix::Bang, Object::Integer(5), Object::Boolean(false)),
+            (Prefix::Bang, Object::Integer(0), Object::Boolean(false)),
+            (Prefix::Minus, Object::Integer(5), Object::Integer(-5)),
+            (Prefix::Minus, Object::Integer(-5), Object::Integer(5)),
+            (Prefix::Plus, Object::Integer(3), Object::Integer(3)),
+            (
+                Prefix::Minus,
+                Object::Integer(i64::MIN),
+                Object::Error(String::from("integer overflow")),
+            ),
+            (
+                Prefix::Minus,
+                Object::Boolean(true),
+                Object::Error(String::from("unknown operator: -BOOLEAN")),
+            ),
+            (
+                Prefix::Plus,
+                Object::StringVal(String::from("a")),
+                Object::Error(String::from("unknown operator: +STRING")),
+            ),
+        ];
+
+        for (prefix, right, expected) in tests {
+            let description = format!("{:?} {:?}", prefix, right);
+            let result = eval_prefix_expr(&prefix, right);
+            if result != expected {
+                panic!(
+                    "{} wrong. expected={:?}, got={:?}",
+                    description, expected, result
+                );
+            }
+        }
+
+        let tests: Vec<(&str, Object)> = vec![
+            ("!true", Object::Boolean(false)),
+            ("!!5", Object::Boolean(true)),
+            ("-(-5)", Object::Integer(5)),
+            ("+3", Object::Integer(3)),
+            (
+                "-true",
+                Object::Error(String::from("unknown operator: -BOOLEAN")),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input)).parse_program();
+            let result = eval(&program, &mut Environment::new());
+            if result != expected {
+                panic!(
+                    "eval({:?}) wrong. expected={:?}, got={:?}",
+                    input, expected, result
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval_infix_expr() {
+        let int = Object::Integer;
+        let boolean = Object::Boolean;
+        let string = |s: &str| Object::StringVal(String::from(s));
+        let error = |s: &str| Object::Error(String::from(s));
+
+        let tests: Vec<(Infix, Object, Object, Object)> = vec![
+            (Infix::Plus, int(5), int(3), int(8)),
+            (Infix::Minus, int(5), int(3), int(2)),
+            (Infix::Asterisk, int(5), int(3), int(15)),
+            (Infix::Slash, int(7), int(2), int(3)),
+            (Infix::Lt, int(5), int(3), boolean(false)),
+            (Infix::Gt, int(5), int(3), boolean(true)),
+            (Infix::Equal, int(5), int(5), boolean(true)),
+            (Infix::NotEqual, int(5), int(5), boolean(false)),
+            (Infix::Equal, boolean(true), boolean(true), boolean(true)),
+            (
+                Infix::NotEqual,
+                boolean(true),
+                boolean(false),
+                boolean(true),
+            ),
+            (Infix::Equal, string("a"), string("a"), boolean(true)),
+            (Infix::NotEqual, string("a"), string("b"), boolean(true)),
+            (Infix::Slash, int(1), int(0), error("division by zero")),
+            (
+                Infix::Plus,
+                int(i64::MAX),
+                int(1),
+                error("integer overflow"),
+            ),
+            (
+                Infix::Slash,
+                int(i64::MIN),
+                int(-1),
+                error("integer overflow"),
+            ),
+            (
+                Infix::Plus,
+                boolean(true),
+                int(1),
+                error("type mismatch: BOOLEAN + INTEGER"),
+            ),
+            (
+                Infix::Asterisk,
+                string("a"),
+                int(2),
+                error("type mismatch: STRING * INTEGER"),
+            ),
+            (
+                Infix::Plus,
+                boolean(true),
+                boolean(false),
+                error("unknown operator: BOOLEAN + BOOLEAN"),
+            ),
+            (
+                Infix::Lt,
+                boolean(true),
+                boolean(false),
+                error("unknown operator: BOOLEAN < BOOLEAN"),
+            ),
+            (
+                Infix::Minus,
+                string("a"),
+                string("b"),
+                error("unknown operator: STRING - STRING"),
+            ),
+            (
+                Infix::Equal,
+                Object::Null,
+                Object::Null,
+                error("unknown operator: NULL == NULL"),
+            ),
+        ];
+
+        for (infix, left, right, expected) in tests {
+            let description = format!("{:?} {:?} {:?}", left, infix, right);
+            let result = eval_infix_expr(&infix, left, right);
+            if result != expected {
+                panic!(
+                    "{} wrong. expected={:?}, got={:?}",
+                    description, expected, result
+                );
+            }
+        }
+    }
+}
@@ -1,46 +1,219 @@
+use std::collections::HashMap;
+use std::io::{BufRead, ErrorKind};
+
 use crate::token::Token;
 
-pub struct Lexer {
-    input: String,
+/// Optional lexer behaviour, all disabled by default
+#[derive(Clone, Debug, Default)]
+pub struct LexerConfig {
+    /// Allow identifiers to start with `$`, e.g. `$request`
+    pub dollar_idents: bool,
+    /// Match keywords regardless of case, e.g. `LET` and `Let` lex as
+    /// `Token::Let`; identifiers keep their spelling
+    pub case_insensitive_keywords: bool,
+    /// Reject raw newlines inside string literals; an unterminated line
+    /// lexes as `Token::Illegal`
+    pub single_line_strings: bool,
+    /// Treat `#` as the start of a line comment
+    pub hash_comments: bool,
+    /// Insert a `Token::Semicolon` at a newline that follows a token which
+    /// can end a statement. A line ending in an operator continues, and no
+    /// semicolon is inserted inside `(...)` or `[...]`, before a closing
+    /// bracket or before `else`.
+    pub newline_terminates: bool,
+}
+
+pub struct Lexer<'a> {
+    /// Buffered input, starting at byte `offset` of the source
+    input: Vec<u8>,
+    reader: Option<Box<dyn BufRead + 'a>>,
+    config: LexerConfig,
+    /// Bytes of the source dropped from the front of `input`
+    offset: usize,
+    /// Start of the token being read; bytes before it can be dropped
+    token_start: usize,
     position: usize,
     read_position: usize,
     ch: u8,
+    /// The reader failed before the end of its input
+    read_failed: bool,
+    /// A newline was skipped since the last token
+    newline_seen: bool,
+    /// The last token can end a statement
+    stmt_may_end: bool,
+    /// Opening brackets not closed yet, innermost last
+    open_brackets: Vec<Token>,
 }
 
-impl Lexer {
-    pub fn new(input: &str) -> Lexer {
+impl<'a> Lexer<'a> {
+    pub fn new(input: &str) -> Lexer<'a> {
+        Lexer::with_config(input, LexerConfig::default())
+    }
+
+    pub fn with_config(input: &str, config: LexerConfig) -> Lexer<'a> {
+        Lexer::with_source(input.as_bytes().to_vec(), None, config)
+    }
+
+    /// Lex incrementally from a reader, pulling more input only when the
+    /// buffered bytes run out. Bytes before the current token are dropped
+    /// on each refill, so memory is bounded by the longest token rather
+    /// than the input. Interrupted reads are retried; any other read error
+    /// lexes as `Token::Illegal` followed by EOF.
+    pub fn from_reader(r: impl BufRead + 'a) -> Lexer<'a> {
+        Lexer::from_reader_with_config(r, LexerConfig::default())
+    }
+
+    pub fn from_reader_with_config(r: impl BufRead + 'a, config: LexerConfig) -> Lexer<'a> {
+        Lexer::with_source(vec![], Some(Box::new(r)), config)
+    }
+
+    fn with_source(
+        input: Vec<u8>,
+        reader: Option<Box<dyn BufRead + 'a>>,
+        config: LexerConfig,
+    ) -> Lexer<'a> {
         let mut l = Lexer {
-            input: input.to_string(),
+            input,
+            reader,
+            config,
+            offset: 0,
+            token_start: 0,
             position: 0,
             read_position: 0,
             ch: 0,
+            read_failed: false,
+            newline_seen: false,
+            stmt_may_end: false,
+            open_brackets: vec![],
         };
         l.read_char();
+        l.skip_shebang();
         l
     }
 
-    fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = 0;
-        } else {
-            self.ch = self.input.as_bytes()[self.read_position];
+    /// Skip a `#!...` line, only valid as the very first line of the input
+    fn skip_shebang(&mut self) {
+        if self.ch != b'#' || self.peek_char() != b'!' {
+            return;
+        }
+        while self.ch != b'\n' && self.ch != 0 {
+            self.read_char();
         }
+    }
+
+    /// Make sure the byte at `position` is buffered if the reader has it,
+    /// dropping the bytes before the current token first
+    fn fill_to(&mut self, position: usize) {
+        while position >= self.offset + self.input.len() {
+            let reader = match self.reader {
+                Some(ref mut reader) => reader,
+                None => return,
+            };
+
+            self.input.drain(..self.token_start - self.offset);
+            self.offset = self.token_start;
+
+            let len = match reader.fill_buf() {
+                Ok(buf) if !buf.is_empty() => {
+                    self.input.extend_from_slice(buf);
+                    buf.len()
+                }
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Ok(_) => {
+                    self.reader = None;
+                    return;
+                }
+                Err(_) => {
+                    self.reader = None;
+                    self.read_failed = true;
+                    return;
+                }
+            };
+            reader.consume(len);
+        }
+    }
+
+    fn read_char(&mut self) {
+        self.ch = self.peek_char();
         self.position = self.read_position;
         self.read_position += 1;
     }
 
-    fn peek_char(&self) -> u8 {
-        if self.read_position >= self.input.len() {
-            0
-        } else {
-            self.input.as_bytes()[self.read_position]
+    fn peek_char(&mut self) -> u8 {
+        self.fill_to(self.read_position);
+        match self.input.get(self.read_position - self.offset) {
+            Some(ch) => *ch,
+            None => 0,
         }
     }
 
-    pub fn next_token(&mut self) -> Token {
-        let tok: Token;
+    /// Slice of the input between `start` and the current position, or
+    /// `None` if it is not valid UTF-8
+    fn slice_from(&self, start: usize) -> Option<&str> {
+        std::str::from_utf8(&self.input[start - self.offset..self.position - self.offset]).ok()
+    }
 
+    /// Next token along with the byte offset it starts at
+    fn next_token_with_offset(&mut self) -> (usize, Token) {
         self.skip_whitespace();
+        let offset = self.position;
+        (offset, self.next_token())
+    }
+
+    pub fn next_token(&mut self) -> Token {
+        self.token_start = self.position;
+        self.skip_whitespace();
+        self.token_start = self.position;
+
+        if self.newline_seen && self.stmt_may_end && self.newline_ends_stmt() {
+            self.stmt_may_end = false;
+            return Token::Semicolon;
+        }
+
+        let tok = self.read_token();
+        match tok {
+            Token::Lparen | Token::Lbracket | Token::Lbrace => self.open_brackets.push(tok.clone()),
+            Token::Rparen | Token::Rbracket | Token::Rbrace => {
+                self.open_brackets.pop();
+            }
+            _ => {}
+        }
+        self.newline_seen = false;
+        self.stmt_may_end = self.config.newline_terminates && ends_stmt(&tok);
+        tok
+    }
+
+    /// Whether the newline before the current character ends a statement,
+    /// given that the previous token could end one
+    fn newline_ends_stmt(&mut self) -> bool {
+        let in_group = matches!(
+            self.open_brackets.last(),
+            Some(Token::Lparen) | Some(Token::Lbracket)
+        );
+        let closing = matches!(self.ch, b')' | b']' | b'}');
+        !in_group && !closing && !self.at_word("else")
+    }
+
+    /// Whether the input at the current position is `word`, not followed by
+    /// more identifier characters
+    fn at_word(&mut self, word: &str) -> bool {
+        let end = self.position + word.len();
+        self.fill_to(end);
+        let (start, end) = (self.position - self.offset, end - self.offset);
+        let found = match self.input.get(start..end) {
+            Some(found) => found,
+            None => return false,
+        };
+        let matches = if self.config.case_insensitive_keywords {
+            found.eq_ignore_ascii_case(word.as_bytes())
+        } else {
+            found == word.as_bytes()
+        };
+        matches && !self.input.get(end).is_some_and(is_letter)
+    }
+
+    fn read_token(&mut self) -> Token {
+        let tok: Token;
 
         match self.ch {
             b'=' => {
@@ -67,13 +240,21 @@ impl Lexer {
             b'>' => tok = Token::Gt,
             b',' => tok = Token::Comma,
             b';' => tok = Token::Semicolon,
+            b':' => tok = Token::Colon,
             b'(' => tok = Token::Lparen,
             b')' => tok = Token::Rparen,
             b'{' => tok = Token::Lbrace,
             b'}' => tok = Token::Rbrace,
+            b'[' => tok = Token::Lbracket,
+            b']' => tok = Token::Rbracket,
+            b'"' => tok = self.consume_string(),
+            0 if self.read_failed => {
+                self.read_failed = false;
+                tok = Token::Illegal;
+            }
             0 => tok = Token::EOF,
             _ => {
-                if is_letter(&self.ch) {
+                if is_letter(&self.ch) || self.is_dollar_ident_start() {
                     tok = self.consume_identifier();
                     return tok;
                 } else if is_digit(&self.ch) {
@@ -90,64 +271,270 @@ impl Lexer {
         tok
     }
 
-    fn read_identifier(&mut self) -> &str {
+    fn is_dollar_ident_start(&mut self) -> bool {
+        self.config.dollar_idents && self.ch == b'$' && is_letter(&self.peek_char())
+    }
+
+    fn read_identifier(&mut self) -> Option<&str> {
         let position = self.position;
+        if self.ch == b'$' {
+            self.read_char();
+        }
         while is_letter(&self.ch) {
             self.read_char();
         }
-        &self.input[position..self.position]
+        self.slice_from(position)
     }
 
-    fn read_number(&mut self) -> &str {
+    /// Read an integer, or a float when the digits are followed by `.` and
+    /// another digit
+    fn read_number(&mut self) -> Option<&str> {
         let position = self.position;
         while is_digit(&self.ch) {
             self.read_char();
         }
-        &self.input[position..self.position]
+        if self.ch == b'.' && is_digit(&self.peek_char()) {
+            self.read_char();
+            while is_digit(&self.ch) {
+                self.read_char();
+            }
+        }
+        self.slice_from(position)
+    }
+
+    /// Read the contents of a string literal, stopping on the closing quote
+    /// or at the end of input
+    fn read_string(&mut self) -> Option<&str> {
+        let position = self.position + 1;
+        let single_line = self.config.single_line_strings;
+        loop {
+            self.read_char();
+            if self.ch == b'"' || self.ch == 0 || (single_line && self.ch == b'\n') {
+                break;
+            }
+        }
+        self.slice_from(position)
     }
 
     fn consume_identifier(&mut self) -> Token {
-        let literal = self.read_identifier();
-        match literal {
-            "fn" => return Token::Fn,
-            "let" => return Token::Let,
-            "true" => return Token::True,
-            "false" => return Token::False,
-            "if" => return Token::If,
-            "else" => return Token::Else,
-            "return" => return Token::Return,
-            _ => return Token::Ident(literal.to_string()),
+        let case_insensitive = self.config.case_insensitive_keywords;
+        let literal = match self.read_identifier() {
+            Some(literal) => literal,
+            None => return Token::Illegal,
+        };
+        let keyword = if case_insensitive {
+            literal.to_ascii_lowercase()
+        } else {
+            literal.to_string()
+        };
+
+        match keyword.as_str() {
+            "fn" => Token::Fn,
+            "let" => Token::Let,
+            "true" => Token::True,
+            "false" => Token::False,
+            "if" => Token::If,
+            "else" => Token::Else,
+            "return" => Token::Return,
+            _ => Token::Ident(literal.to_string()),
+        }
+    }
+
+    fn consume_string(&mut self) -> Token {
+        let string = self.read_string().map(str::to_string);
+        match string {
+            Some(string) if self.ch == b'"' => Token::Str(string),
+            _ => Token::Illegal,
         }
     }
 
     fn consume_number(&mut self) -> Token {
-        Token::Int(self.read_number().parse::<i64>().unwrap())
+        let literal = match self.read_number() {
+            Some(literal) => literal,
+            None => return Token::Illegal,
+        };
+        if literal.contains('.') {
+            return Lexer::consume_float(literal);
+        }
+        match literal.parse::<i64>() {
+            Ok(int) => Token::Int(int),
+            Err(_) => Token::Illegal,
+        }
+    }
+
+    fn consume_float(literal: &str) -> Token {
+        match literal.parse::<f64>() {
+            Ok(float) => Token::Float(float),
+            Err(_) => Token::Illegal,
+        }
     }
 
+    /// Skip whitespace, and `#` line comments when enabled
     fn skip_whitespace(&mut self) {
-        while self.ch == b' ' || self.ch == b'\t' || self.ch == b'\n' || self.ch == b'\r' {
-            self.read_char();
+        loop {
+            while self.ch == b' ' || self.ch == b'\t' || self.ch == b'\n' || self.ch == b'\r' {
+                if self.ch == b'\n' {
+                    self.newline_seen = true;
+                }
+                self.read_char();
+            }
+
+            if !(self.config.hash_comments && self.ch == b'#') {
+                break;
+            }
+            while self.ch != b'\n' && self.ch != 0 {
+                self.read_char();
+            }
         }
     }
 }
 
-fn is_letter(ch: &u8) -> bool {
-    match ch {
-        b'a'...b'z' | b'A'...b'Z' | b'_' => return true,
-        _ => return false,
+/// Whether a statement can end after `tok`
+fn ends_stmt(tok: &Token) -> bool {
+    matches!(
+        tok,
+        Token::Ident(_)
+            | Token::Int(_)
+            | Token::Float(_)
+            | Token::Str(_)
+            | Token::True
+            | Token::False
+            | Token::Rparen
+            | Token::Rbracket
+            | Token::Rbrace
+    )
+}
+
+/// An illegal character found while lexing
+#[derive(Debug, PartialEq)]
+pub struct LexError {
+    pub ch: char,
+    /// Byte offset of the character in the input
+    pub position: usize,
+}
+
+/// Lex the whole input, including the trailing EOF token
+pub fn tokenize(input: &str) -> Vec<Token> {
+    tokenize_with_offsets(input)
+        .into_iter()
+        .map(|(_, tok)| tok)
+        .collect()
+}
+
+/// Lex the whole input, failing with every illegal character found
+pub fn tokenize_checked(input: &str) -> Result<Vec<Token>, Vec<LexError>> {
+    let tokens = tokenize_with_offsets(input);
+
+    // A multi-byte character lexes as one Illegal token per byte; report it
+    // once, at the byte where it starts.
+    let errors: Vec<LexError> = tokens
+        .iter()
+        .filter(|(_, tok)| *tok == Token::Illegal)
+        .filter_map(|(position, _)| {
+            input
+                .get(*position..)
+                .and_then(|rest| rest.chars().next())
+                .map(|ch| LexError {
+                    ch,
+                    position: *position,
+                })
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(tokens.into_iter().map(|(_, tok)| tok).collect())
+    } else {
+        Err(errors)
     }
 }
 
-fn is_digit(ch: &u8) -> bool {
-    match ch {
-        b'0'...b'9' => return true,
-        _ => return false,
+fn tokenize_with_offsets(input: &str) -> Vec<(usize, Token)> {
+    let mut lexer = Lexer::new(input);
+    let mut tokens = vec![];
+
+    loop {
+        let (offset, tok) = lexer.next_token_with_offset();
+        let is_eof = tok == Token::EOF;
+        tokens.push((offset, tok));
+        if is_eof {
+            break;
+        }
+    }
+
+    tokens
+}
+
+/// Count the tokens of the input, excluding EOF
+pub fn token_count(input: &str) -> usize {
+    tokenize(input).len() - 1
+}
+
+/// Render one token per line with its 1-based `line:column`, e.g.
+/// `Int(5) @1:1`, for snapshot tests
+pub fn debug_tokens(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = String::new();
+    let (mut line, mut column, mut position) = (1, 1, 0);
+
+    for (offset, tok) in tokenize_with_offsets(input) {
+        let offset = offset.min(bytes.len());
+        for byte in &bytes[position..offset] {
+            if *byte == b'\n' {
+                line += 1;
+                column = 1;
+            } else if *byte & 0xC0 != 0x80 {
+                // count characters, not UTF-8 continuation bytes
+                column += 1;
+            }
+        }
+        position = offset;
+
+        out.push_str(&format!("{:?} @{}:{}\n", tok, line, column));
     }
+
+    out
+}
+
+/// Count how often each token kind occurs, ignoring payloads and EOF
+pub fn token_histogram(input: &str) -> HashMap<String, usize> {
+    let mut histogram = HashMap::new();
+
+    for tok in tokenize(input) {
+        if tok == Token::EOF {
+            continue;
+        }
+        *histogram.entry(tok.kind_name().to_string()).or_insert(0) += 1;
+    }
+
+    histogram
+}
+
+/// Assert that both inputs lex to the same token stream
+#[cfg(test)]
+pub fn assert_same_tokens(a: &str, b: &str) {
+    let (a_tokens, b_tokens) = (tokenize(a), tokenize(b));
+    if a_tokens != b_tokens {
+        panic!(
+            "token streams differ.\n{:?} => {:?}\n{:?} => {:?}",
+            a, a_tokens, b, b_tokens
+        );
+    }
+}
+
+fn is_letter(ch: &u8) -> bool {
+    matches!(ch, b'a'..=b'z' | b'A'..=b'Z' | b'_')
+}
+
+fn is_digit(ch: &u8) -> bool {
+    ch.is_ascii_digit()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::to_sexp;
+    use crate::parser::{check_parser_errors, Parser};
+    use std::io::{self, BufReader, Cursor, Read};
 
     #[test]
     fn test_next_token() {
@@ -170,6 +557,8 @@ if (5 < 10) {
 
 10 == 10;
 10 != 9;
+[1, 2];
+{\"foo\": \"bar\"}
 ";
 
         let types: Vec<Token> = vec![
@@ -256,6 +645,19 @@ if (5 < 10) {
             Token::NotEqual,
             Token::Int(9),
             Token::Semicolon,
+            //
+            Token::Lbracket,
+            Token::Int(1),
+            Token::Comma,
+            Token::Int(2),
+            Token::Rbracket,
+            Token::Semicolon,
+            //
+            Token::Lbrace,
+            Token::Str(String::from("foo")),
+            Token::Colon,
+            Token::Str(String::from("bar")),
+            Token::Rbrace,
             Token::EOF,
         ];
 
@@ -272,4 +674,606 @@ if (5 < 10) {
             }
         }
     }
+
+    #[test]
+    fn test_token_count() {
+        let tests: Vec<(&str, usize)> = vec![
+            ("", 0),
+            ("let five = 5;", 5),
+            ("let add = fn(x, y) { x + y; };", 16),
+        ];
+
+        for (input, expected) in tests {
+            let count = token_count(input);
+            if count != expected {
+                panic!(
+                    "token_count({:?}) wrong. expected={}, got={}",
+                    input, expected, count
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_reader() {
+        const INPUT: &str = "let five = 5;
+let add = fn(x, y) {
+    x + y;
+};
+let result = add(five, 10) != 15;
+";
+
+        // A tiny buffer makes identifiers and `!=` straddle buffer fills.
+        let mut lexer = Lexer::from_reader(BufReader::with_capacity(3, Cursor::new(INPUT)));
+
+        for (i, token) in tokenize(INPUT).iter().enumerate() {
+            let tok = lexer.next_token();
+
+            if tok != *token {
+                panic!(
+                    "tokens[{}] - token wrong. expected={:?}, got={:?}",
+                    i, token, tok
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_reader_drops_consumed_input() {
+        let input = "let total = one + 22 * \"three\";\n".repeat(1000);
+
+        // A borrowed reader, which need not be 'static
+        let mut lexer = Lexer::from_reader(BufReader::with_capacity(16, input.as_bytes()));
+        let mut max_buffered = 0;
+        for (i, token) in tokenize(&input).iter().enumerate() {
+            let tok = lexer.next_token();
+            if tok != *token {
+                panic!(
+                    "tokens[{}] - token wrong. expected={:?}, got={:?}",
+                    i, token, tok
+                );
+            }
+            max_buffered = max_buffered.max(lexer.input.len());
+        }
+
+        if max_buffered > 32 {
+            panic!("buffered too much input. got={} bytes", max_buffered);
+        }
+    }
+
+    #[test]
+    fn test_from_reader_with_config() {
+        const INPUT: &str = "$x # note\n";
+
+        let config = LexerConfig {
+            dollar_idents: true,
+            hash_comments: true,
+            newline_terminates: true,
+            ..LexerConfig::default()
+        };
+        let mut lexer = Lexer::from_reader_with_config(Cursor::new(INPUT), config);
+        let expected = [
+            Token::Ident(String::from("$x")),
+            Token::Semicolon,
+            Token::EOF,
+        ];
+        for token in expected {
+            let tok = lexer.next_token();
+            if tok != token {
+                panic!("token wrong. expected={:?}, got={:?}", token, tok);
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_reader_errors() {
+        /// Reader that fails with all but the last of `errors`, yields
+        /// `data`, then fails with the last error
+        struct FlakyReader {
+            data: Vec<u8>,
+            errors: Vec<ErrorKind>,
+        }
+
+        impl Read for FlakyReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.data.is_empty() || self.errors.len() > 1 {
+                    return match self.errors.pop() {
+                        Some(kind) => Err(io::Error::from(kind)),
+                        None => Ok(0),
+                    };
+                }
+                let len = self.data.len().min(buf.len());
+                buf[..len].copy_from_slice(&self.data[..len]);
+                self.data.drain(..len);
+                Ok(len)
+            }
+        }
+
+        let tests: Vec<(Vec<ErrorKind>, Vec<Token>)> = vec![
+            (
+                vec![ErrorKind::Interrupted, ErrorKind::Interrupted],
+                vec![Token::Int(1), Token::Plus, Token::Int(2), Token::EOF],
+            ),
+            (
+                vec![ErrorKind::Other],
+                vec![
+                    Token::Int(1),
+                    Token::Plus,
+                    Token::Int(2),
+                    Token::Illegal,
+                    Token::EOF,
+                ],
+            ),
+        ];
+
+        for (errors, expected) in tests {
+            let reader = FlakyReader {
+                data: b"1 + 2".to_vec(),
+                errors: errors.clone(),
+            };
+            let mut lexer = Lexer::from_reader(BufReader::new(reader));
+            for token in &expected {
+                let tok = lexer.next_token();
+                if tok != *token {
+                    panic!(
+                        "{:?} - token wrong. expected={:?}, got={:?}",
+                        errors, token, tok
+                    );
+                }
+            }
+        }
+
+        let mut lexer = Lexer::from_reader(Cursor::new(vec![b'"', 0xff, b'"', b'1']));
+        for token in [Token::Illegal, Token::Int(1), Token::EOF] {
+            let tok = lexer.next_token();
+            if tok != token {
+                panic!("token wrong. expected={:?}, got={:?}", token, tok);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dollar_idents() {
+        let tests: Vec<(bool, Vec<Token>)> = vec![
+            (
+                false,
+                vec![Token::Illegal, Token::Ident(String::from("x")), Token::EOF],
+            ),
+            (true, vec![Token::Ident(String::from("$x")), Token::EOF]),
+        ];
+
+        for (dollar_idents, expected) in tests {
+            let config = LexerConfig {
+                dollar_idents,
+                ..LexerConfig::default()
+            };
+            let mut lexer = Lexer::with_config("$x", config);
+
+            for (i, token) in expected.iter().enumerate() {
+                let tok = lexer.next_token();
+                if tok != *token {
+                    panic!(
+                        "dollar_idents={} tokens[{}] - token wrong. expected={:?}, got={:?}",
+                        dollar_idents, i, token, tok
+                    );
+                }
+            }
+        }
+
+        // A lone `$` is never an identifier
+        let config = LexerConfig {
+            dollar_idents: true,
+            ..LexerConfig::default()
+        };
+        let tok = Lexer::with_config("$ x", config).next_token();
+        if tok != Token::Illegal {
+            panic!("token wrong. expected=Illegal, got={:?}", tok);
+        }
+    }
+
+    #[test]
+    fn test_shebang() {
+        const PROGRAM: &str = "let x = 5;\nx;\n";
+
+        let with_shebang = format!("#!/usr/bin/env rucky\n{}", PROGRAM);
+        if tokenize(&with_shebang) != tokenize(PROGRAM) {
+            panic!(
+                "shebang not skipped. got={:?}, expected={:?}",
+                tokenize(&with_shebang),
+                tokenize(PROGRAM)
+            );
+        }
+
+        let tokens = tokenize("x;\n#!not a shebang\n");
+        if tokens[2] != Token::Illegal {
+            panic!(
+                "tokens[2] - token wrong. expected=Illegal, got={:?}",
+                tokens[2]
+            );
+        }
+    }
+
+    #[test]
+    fn test_assert_same_tokens() {
+        assert_same_tokens("1+2", "1 + 2");
+        assert_same_tokens("let x=5;", "let  x =\n\t5 ;");
+    }
+
+    #[test]
+    #[should_panic(expected = "token streams differ")]
+    fn test_assert_same_tokens_differ() {
+        assert_same_tokens("1+2", "1 - 2");
+    }
+
+    #[test]
+    fn test_token_histogram() {
+        let histogram = token_histogram("1 + 2 + x; 3");
+
+        let tests: Vec<(&str, usize)> = vec![("Int", 3), ("Plus", 2), ("Ident", 1), ("EOF", 0)];
+
+        for (kind, expected) in tests {
+            let count = histogram.get(kind).cloned().unwrap_or(0);
+            if count != expected {
+                panic!("{} count wrong. expected={}, got={}", kind, expected, count);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokenize_checked() {
+        match tokenize_checked("let x = 5;") {
+            Ok(tokens) => {
+                if tokens != tokenize("let x = 5;") {
+                    panic!("tokens wrong. got={:?}", tokens);
+                }
+            }
+            Err(errors) => panic!("unexpected errors: {:?}", errors),
+        }
+
+        let tests: Vec<(&str, Vec<LexError>)> = vec![
+            (
+                "let x = @;",
+                vec![LexError {
+                    ch: '@',
+                    position: 8,
+                }],
+            ),
+            (
+                "a @ b é",
+                vec![
+                    LexError {
+                        ch: '@',
+                        position: 2,
+                    },
+                    LexError {
+                        ch: 'é',
+                        position: 6,
+                    },
+                ],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            match tokenize_checked(input) {
+                Ok(tokens) => panic!("expected errors for {:?}. got={:?}", input, tokens),
+                Err(errors) => {
+                    if errors != expected {
+                        panic!("errors wrong. expected={:?}, got={:?}", expected, errors);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fn_keyword() {
+        let tok = Lexer::new("fn").next_token();
+        if tok != Token::Fn {
+            panic!("token wrong. expected={:?}, got={:?}", Token::Fn, tok);
+        }
+    }
+
+    #[test]
+    fn test_case_insensitive_keywords() {
+        const INPUT: &str = "IF (True) { Let Foo = 1; }";
+
+        let tests: Vec<(bool, Vec<Token>)> = vec![
+            (
+                true,
+                vec![
+                    Token::If,
+                    Token::Lparen,
+                    Token::True,
+                    Token::Rparen,
+                    Token::Lbrace,
+                    Token::Let,
+                    Token::Ident(String::from("Foo")),
+                    Token::Assign,
+                    Token::Int(1),
+                    Token::Semicolon,
+                    Token::Rbrace,
+                    Token::EOF,
+                ],
+            ),
+            (
+                false,
+                vec![
+                    Token::Ident(String::from("IF")),
+                    Token::Lparen,
+                    Token::Ident(String::from("True")),
+                    Token::Rparen,
+                    Token::Lbrace,
+                    Token::Ident(String::from("Let")),
+                    Token::Ident(String::from("Foo")),
+                    Token::Assign,
+                    Token::Int(1),
+                    Token::Semicolon,
+                    Token::Rbrace,
+                    Token::EOF,
+                ],
+            ),
+        ];
+
+        for (case_insensitive_keywords, expected) in tests {
+            let config = LexerConfig {
+                case_insensitive_keywords,
+                ..LexerConfig::default()
+            };
+            let mut lexer = Lexer::with_config(INPUT, config);
+
+            for (i, token) in expected.iter().enumerate() {
+                let tok = lexer.next_token();
+                if tok != *token {
+                    panic!(
+                        "case_insensitive_keywords={} tokens[{}] - token wrong. expected={:?}, got={:?}",
+                        case_insensitive_keywords, i, token, tok
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_string() {
+        let tests: Vec<(&str, Vec<Token>)> = vec![
+            (r#""""#, vec![Token::Str(String::new()), Token::EOF]),
+            (
+                r#""foo bar""#,
+                vec![Token::Str(String::from("foo bar")), Token::EOF],
+            ),
+            (
+                r#"let s = "hello";"#,
+                vec![
+                    Token::Let,
+                    Token::Ident(String::from("s")),
+                    Token::Assign,
+                    Token::Str(String::from("hello")),
+                    Token::Semicolon,
+                    Token::EOF,
+                ],
+            ),
+            (r#""unterminated"#, vec![Token::Illegal, Token::EOF]),
+        ];
+
+        for (input, expected) in tests {
+            let tokens = tokenize(input);
+            if tokens != expected {
+                panic!(
+                    "tokenize({}) wrong. expected={:?}, got={:?}",
+                    input, expected, tokens
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_multi_line_string() {
+        const INPUT: &str = "\"one\ntwo\";";
+
+        let tokens = tokenize(INPUT);
+        let expected = vec![
+            Token::Str(String::from("one\ntwo")),
+            Token::Semicolon,
+            Token::EOF,
+        ];
+        if tokens != expected {
+            panic!("tokens wrong. expected={:?}, got={:?}", expected, tokens);
+        }
+
+        // Tokens after the string are on the line the string ends on
+        let snapshot = debug_tokens("\"a\nb\" x");
+        let expected = "Str(\"a\\nb\") @1:1\nIdent(\"x\") @2:4\nEOF @2:5\n";
+        if snapshot != expected {
+            panic!(
+                "debug_tokens wrong.\nexpected:\n{}got:\n{}",
+                expected, snapshot
+            );
+        }
+
+        let config = LexerConfig {
+            single_line_strings: true,
+            ..LexerConfig::default()
+        };
+        let tok = Lexer::with_config(INPUT, config).next_token();
+        if tok != Token::Illegal {
+            panic!("token wrong. expected=Illegal, got={:?}", tok);
+        }
+
+        let tok = Lexer::new("\"one\ntwo").next_token();
+        if tok != Token::Illegal {
+            panic!("token wrong. expected=Illegal, got={:?}", tok);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn test_float() {
+        let tests: Vec<(&str, Vec<Token>)> = vec![
+            ("3.14", vec![Token::Float(3.14), Token::EOF]),
+            ("0.5", vec![Token::Float(0.5), Token::EOF]),
+            ("1.0", vec![Token::Float(1.0), Token::EOF]),
+            ("-3.14", vec![Token::Minus, Token::Float(3.14), Token::EOF]),
+            (
+                "1.x",
+                vec![
+                    Token::Int(1),
+                    Token::Illegal,
+                    Token::Ident(String::from("x")),
+                    Token::EOF,
+                ],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let tokens = tokenize(input);
+            if tokens != expected {
+                panic!(
+                    "tokenize({}) wrong. expected={:?}, got={:?}",
+                    input, expected, tokens
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_int_out_of_range() {
+        let tests: Vec<(&str, Vec<Token>)> = vec![
+            (
+                "9223372036854775807",
+                vec![Token::Int(i64::MAX), Token::EOF],
+            ),
+            ("99999999999999999999", vec![Token::Illegal, Token::EOF]),
+            (
+                "-9223372036854775808",
+                vec![Token::Minus, Token::Illegal, Token::EOF],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let tokens = tokenize(input);
+            if tokens != expected {
+                panic!(
+                    "tokenize({}) wrong. expected={:?}, got={:?}",
+                    input, expected, tokens
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_debug_tokens() {
+        let tests: Vec<(&str, &str)> = vec![
+            (
+                "x = 1",
+                "Ident(\"x\") @1:1\nAssign @1:3\nInt(1) @1:5\nEOF @1:6\n",
+            ),
+            (
+                "\"é\";\n  5",
+                "Str(\"é\") @1:1\nSemicolon @1:4\nInt(5) @2:3\nEOF @2:4\n",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let snapshot = debug_tokens(input);
+            if snapshot != expected {
+                panic!(
+                    "debug_tokens({:?}) wrong.\nexpected:\n{}got:\n{}",
+                    input, expected, snapshot
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_hash_comments() {
+        const INPUT: &str = "5 # note\n+ 6 #";
+
+        let config = LexerConfig {
+            hash_comments: true,
+            ..LexerConfig::default()
+        };
+        let mut lexer = Lexer::with_config(INPUT, config);
+        for token in [Token::Int(5), Token::Plus, Token::Int(6), Token::EOF] {
+            let tok = lexer.next_token();
+            if tok != token {
+                panic!("token wrong. expected={:?}, got={:?}", token, tok);
+            }
+        }
+
+        let tokens = tokenize(INPUT);
+        if tokens[1] != Token::Illegal {
+            panic!(
+                "tokens[1] - token wrong. expected=Illegal, got={:?}",
+                tokens[1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_newline_terminates() {
+        const INPUT: &str = "let x = 1 +\n2\nx;\n";
+
+        let config = LexerConfig {
+            newline_terminates: true,
+            ..LexerConfig::default()
+        };
+        let mut lexer = Lexer::with_config(INPUT, config.clone());
+        let expected = [
+            Token::Let,
+            Token::Ident(String::from("x")),
+            Token::Assign,
+            Token::Int(1),
+            Token::Plus,
+            Token::Int(2),
+            Token::Semicolon,
+            Token::Ident(String::from("x")),
+            Token::Semicolon,
+            Token::EOF,
+        ];
+        for token in expected {
+            let tok = lexer.next_token();
+            if tok != token {
+                panic!("token wrong. expected={:?}, got={:?}", token, tok);
+            }
+        }
+
+        let mut parser = Parser::new(Lexer::with_config("1\n2", config.clone()));
+        let program = parser.parse_program();
+        check_parser_errors(&parser);
+        if program.len() != 2 {
+            panic!("program has not 2 statements. got={:?}", program);
+        }
+
+        let tests: Vec<(&str, &str)> = vec![
+            ("let x = 1 +\n2\nx", "(let x (+ 1 2))\nx"),
+            ("let a = [\n1,\n2\n]\na", "(let a (array 1 2))\na"),
+            ("add(\n1,\n2\n)", "(call add 1 2)"),
+            ("(1\n+ 2)", "(+ 1 2)"),
+            ("let h = {\n\"a\": 1\n}", "(let h (hash (\"a\" 1)))"),
+            ("if (x) {\n1\n}\nelse {\n2\n}\ny", "(if x (do 1) (do 2))\ny"),
+            (
+                "f(fn(x) {\nlet y = x\ny\n})",
+                "(call f (fn (x) (let y x) y))",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::with_config(input, config.clone()));
+            let program = parser.parse_program();
+            check_parser_errors(&parser);
+
+            let sexp = to_sexp(&program);
+            if sexp != expected {
+                panic!(
+                    "{:?} parsed wrong. expected={}, got={}",
+                    input, expected, sexp
+                );
+            }
+        }
+
+        let tokens = tokenize("1\n2");
+        if tokens.contains(&Token::Semicolon) {
+            panic!("semicolon inserted without the flag. got={:?}", tokens);
+        }
+    }
 }
@@ -1,4 +1,8 @@
+pub mod arena;
 pub mod ast;
+pub mod environment;
+pub mod evaluator;
 pub mod lexer;
+pub mod object;
 pub mod parser;
 pub mod token;
@@ -13,6 +13,33 @@ fn get_input() -> String {
     s.trim().to_string()
 }
 
+/// Split a REPL line on commas that are not nested in (), [], {} or a
+/// string literal, so `1, foo(2), 3` is treated as three expressions
+fn split_top_level_commas(line: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut in_string = false;
+
+    for ch in line.chars() {
+        match ch {
+            '"' => in_string = !in_string,
+            '(' | '[' | '{' if !in_string => depth += 1,
+            ')' | ']' | '}' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                parts.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(ch);
+    }
+    parts.push(current.trim().to_string());
+
+    parts
+}
+
 fn main() {
     loop {
         print!("{}", PROMPT);
@@ -24,14 +51,38 @@ fn main() {
             break;
         }
 
-        let mut l = Lexer::new(&line);
+        for expr in split_top_level_commas(&line) {
+            let mut l = Lexer::new(&expr);
+
+            loop {
+                let tok = l.next_token();
+                if tok == Token::EOF {
+                    break;
+                }
+                println!("{:?}", tok);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_top_level_commas() {
+        let tests: Vec<(&str, Vec<&str>)> = vec![
+            ("1", vec!["1"]),
+            ("1, foo(2), 3", vec!["1", "foo(2)", "3"]),
+            ("add(1, 2), [3, 4]", vec!["add(1, 2)", "[3, 4]"]),
+            (r#""a, b", c"#, vec![r#""a, b""#, "c"]),
+        ];
 
-        loop {
-            let tok = l.next_token();
-            if tok == Token::EOF {
-                break;
+        for (input, expected) in tests {
+            let parts = split_top_level_commas(input);
+            if parts != expected {
+                panic!("got={:?}. expected={:?}", parts, expected);
             }
-            println!("{:?}", tok);
         }
     }
 }
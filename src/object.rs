@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::ast::{BlockStmt, Ident};
+use crate::environment::Environment;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Object {
+    Integer(i64),
+    Boolean(bool),
+    StringVal(String),
+    Array(Vec<Object>),
+    Hash(HashMap<HashKey, HashPair>),
+    Function {
+        params: Vec<Ident>,
+        body: BlockStmt,
+        env: Environment,
+    },
+    Null,
+    Error(String),
+    ReturnValue(Box<Object>),
+}
+
+impl Object {
+    /// Type name used in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) => "INTEGER",
+            Object::Boolean(_) => "BOOLEAN",
+            Object::StringVal(_) => "STRING",
+            Object::Array(_) => "ARRAY",
+            Object::Hash(_) => "HASH",
+            Object::Function { .. } => "FUNCTION",
+            Object::Null => "NULL",
+            Object::Error(_) => "ERROR",
+            Object::ReturnValue(_) => "RETURN_VALUE",
+        }
+    }
+
+    /// `false` and `null` are falsy, every other value is truthy
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Object::Boolean(false) | Object::Null)
+    }
+}
+
+/// Values usable as hash keys
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+}
+
+/// Entry of a hash, keeping the original key object next to its value
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashPair {
+    pub key: Object,
+    pub value: Object,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Expr, Stmt};
+
+    #[test]
+    fn test_object_variants() {
+        let mut pairs = HashMap::new();
+        pairs.insert(
+            HashKey::String(String::from("a")),
+            HashPair {
+                key: Object::StringVal(String::from("a")),
+                value: Object::Integer(1),
+            },
+        );
+
+        let objects = vec![
+            Object::Integer(5),
+            Object::Boolean(true),
+            Object::StringVal(String::from("foo")),
+            Object::Array(vec![Object::Integer(1), Object::Null]),
+            Object::Hash(pairs),
+            Object::Function {
+                params: vec![Ident(String::from("x"))],
+                body: vec![Stmt::Expr(Expr::Ident(Ident(String::from("x"))))],
+                env: Environment::new(),
+            },
+            Object::Null,
+            Object::Error(String::from("boom")),
+            Object::ReturnValue(Box::new(Object::Integer(1))),
+        ];
+
+        for (i, object) in objects.iter().enumerate() {
+            if object.clone() != *object {
+                panic!("objects[{}] - clone differs. got={:?}", i, object);
+            }
+            for (j, other) in objects.iter().enumerate() {
+                if i != j && object == other {
+                    panic!("objects[{}] equals objects[{}]. got={:?}", i, j, object);
+                }
+            }
+        }
+
+        if HashKey::Integer(1) == HashKey::Boolean(true) {
+            panic!("HashKey::Integer(1) equals HashKey::Boolean(true)");
+        }
+    }
+
+    #[test]
+    fn test_is_truthy() {
+        let tests: Vec<(Object, bool)> = vec![
+            (Object::Boolean(true), true),
+            (Object::Boolean(false), false),
+            (Object::Null, false),
+            (Object::Integer(0), true),
+            (Object::StringVal(String::new()), true),
+            (Object::Array(vec![]), true),
+        ];
+
+        for (object, expected) in tests {
+            if object.is_truthy() != expected {
+                panic!(
+                    "{:?}.is_truthy() wrong. expected={}, got={}",
+                    object, expected, !expected
+                );
+            }
+        }
+    }
+}
@@ -2,20 +2,41 @@ use crate::ast::*;
 use crate::lexer::Lexer;
 use crate::token::Token;
 
-pub struct Parser {
-    l: Lexer,
+/// Number of errors collected before the parser gives up
+pub const DEFAULT_MAX_ERRORS: usize = 100;
+
+const TOO_MANY_ERRORS: &str = "too many errors";
+
+/// Deepest expression nesting parsed before reporting an error, keeping
+/// generated input from overflowing the stack
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+pub struct Parser<'a> {
+    l: Lexer<'a>,
     cur_token: Token,
     peek_token: Token,
     errors: Vec<String>,
+    max_errors: usize,
+    depth: usize,
+    max_depth: usize,
 }
 
-impl Parser {
-    pub fn new(l: Lexer) -> Parser {
+impl<'a> Parser<'a> {
+    pub fn new(l: Lexer<'a>) -> Parser<'a> {
+        Parser::with_max_errors(l, DEFAULT_MAX_ERRORS)
+    }
+
+    /// Stop parsing after `max_errors` errors, leaving a final
+    /// "too many errors" entry
+    pub fn with_max_errors(l: Lexer<'a>, max_errors: usize) -> Parser<'a> {
         let mut p = Parser {
             l,
             cur_token: Token::EOF,
             peek_token: Token::EOF,
             errors: vec![],
+            max_errors,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
         };
 
         p.next_token();
@@ -24,18 +45,28 @@ impl Parser {
         p
     }
 
+    /// Report an error instead of parsing expressions nested deeper than
+    /// `max_depth`, counting groups, prefixes, literals and blocks
+    pub fn with_max_depth(mut self, max_depth: usize) -> Parser<'a> {
+        self.max_depth = max_depth;
+        self
+    }
+
     fn next_token(&mut self) {
         std::mem::swap(&mut self.cur_token, &mut self.peek_token);
         self.peek_token = self.l.next_token();
     }
 
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
     pub fn parse_program(&mut self) -> Program {
         let mut program: Program = vec![];
 
-        while !self.cur_token_is(Token::EOF) {
-            match self.parse_stmt() {
-                Some(stmt) => program.push(stmt),
-                None => {}
+        while !self.cur_token_is(Token::EOF) && !self.too_many_errors() {
+            if let Some(stmt) = self.parse_stmt() {
+                program.push(stmt);
             }
             self.next_token();
         }
@@ -54,15 +85,12 @@ impl Parser {
 
     /// Parse let statement
     fn parse_let_stmt(&mut self) -> Option<Stmt> {
-        match self.peek_token {
-            Token::Ident(_) => self.next_token(),
-            _ => return None,
+        if !self.peek_token.same_kind(&Token::Ident(String::new())) {
+            return None;
         }
+        self.next_token();
 
-        let name = match self.parse_ident() {
-            Some(name) => name,
-            None => return None,
-        };
+        let name = self.parse_ident()?;
 
         if !self.consume_token(Token::Assign) {
             return None;
@@ -70,14 +98,9 @@ impl Parser {
 
         self.next_token();
 
-        let expr = match self.parse_expr(Precedence::Lowest) {
-            Some(expr) => expr,
-            None => return None,
-        };
+        let expr = self.parse_expr(Precedence::Lowest)?;
 
-        while !self.cur_token_is(Token::Semicolon) {
-            self.next_token();
-        }
+        self.skip_semicolon();
 
         Some(Stmt::Let(name, expr))
     }
@@ -86,14 +109,9 @@ impl Parser {
     fn parse_return_stmt(&mut self) -> Option<Stmt> {
         self.next_token();
 
-        let expr = match self.parse_expr(Precedence::Lowest) {
-            Some(expr) => expr,
-            None => return None,
-        };
+        let expr = self.parse_expr(Precedence::Lowest)?;
 
-        while !self.cur_token_is(Token::Semicolon) {
-            self.next_token();
-        }
+        self.skip_semicolon();
 
         Some(Stmt::Return(expr))
     }
@@ -109,47 +127,93 @@ impl Parser {
     fn parse_expr_stmt(&mut self) -> Option<Stmt> {
         match self.parse_expr(Precedence::Lowest) {
             Some(expr) => {
-                self.consume_token(Token::Semicolon);
+                self.skip_semicolon();
                 Some(Stmt::Expr(expr))
             }
             _ => None,
         }
     }
 
-    /// Parse expression
-    fn parse_expr(&mut self, precendence: Precedence) -> Option<Expr> {
+    /// Parse expression, tracking how deeply it is nested
+    fn parse_expr(&mut self, precedence: Precedence) -> Option<Expr> {
+        if self.depth >= self.max_depth {
+            self.push_error(format!(
+                "expression nested deeper than {} levels",
+                self.max_depth
+            ));
+            return None;
+        }
+
+        self.depth += 1;
+        let expr = self.parse_nested_expr(precedence);
+        self.depth -= 1;
+
+        expr
+    }
+
+    fn parse_nested_expr(&mut self, precedence: Precedence) -> Option<Expr> {
         // prefix
-        let left = match self.cur_token {
+        let mut left = match self.cur_token {
             Token::Ident(_) => self.parse_ident_expr(),
             Token::Int(_) => self.parse_int_expr(),
+            Token::Float(_) => self.parse_float_expr(),
+            Token::Str(_) => self.parse_string_expr(),
+            Token::True | Token::False => self.parse_bool_expr(),
             Token::Bang | Token::Plus | Token::Minus => self.parse_prefix_expr(),
-            _ => return None,
-        };
+            Token::Lparen => self.parse_grouped_expr(),
+            Token::Lbracket => self.parse_array_literal(),
+            Token::Lbrace => self.parse_hash_literal(),
+            Token::Fn => self.parse_function_literal(),
+            Token::If => self.parse_if_expr(),
+            _ => {
+                self.push_error(format!("no prefix parse function for {:?}", self.cur_token));
+                return None;
+            }
+        }?;
 
-        if !self.peek_token_is_infix() {
-            return left;
+        // infix
+        while precedence < self.peek_precedence() {
+            self.next_token();
+            left = self.parse_infix_expr(left)?;
         }
 
-        self.next_token();
+        Some(left)
+    }
+
+    /// Parse identifier expression
+    fn parse_ident_expr(&self) -> Option<Expr> {
+        self.parse_ident().map(Expr::Ident)
+    }
 
-        match left {
-            Some(expr) => self.parse_infix_expr(expr),
+    /// Parse integer literal expression
+    fn parse_int_expr(&self) -> Option<Expr> {
+        match self.cur_token {
+            Token::Int(ref int) => Some(Expr::Literal(Literal::Int(*int))),
             _ => None,
         }
     }
 
-    /// Parse identifier expression
-    fn parse_ident_expr(&self) -> Option<Expr> {
-        match self.parse_ident() {
-            Some(ident) => Some(Expr::Ident(ident)),
+    /// Parse float literal expression
+    fn parse_float_expr(&self) -> Option<Expr> {
+        match self.cur_token {
+            Token::Float(float) => Some(Expr::Literal(Literal::Float(float))),
             _ => None,
         }
     }
 
-    /// Parse integer literal expression
-    fn parse_int_expr(&self) -> Option<Expr> {
+    /// Parse string literal expression
+    fn parse_string_expr(&self) -> Option<Expr> {
+        match self.cur_token {
+            Token::Str(ref string) => Some(Expr::Literal(Literal::String(string.clone()))),
+            _ => None,
+        }
+    }
+
+    /// Parse boolean literal expression
+    fn parse_bool_expr(&self) -> Option<Expr> {
         match self.cur_token {
-            Token::Int(ref int) => Some(Expr::Literal(Literal::Int(int.clone()))),
+            Token::True => Some(Expr::Literal(Literal::Bool(true))),
+            Token::False => Some(Expr::Literal(Literal::Bool(false))),
             _ => None,
         }
     }
@@ -165,15 +229,202 @@ impl Parser {
 
         self.next_token();
 
-        match self.parse_expr(Precedence::Lowest) {
-            Some(expr) => Some(Expr::Prefix(prefix, Box::new(expr))),
-            _ => None,
+        self.parse_expr(Precedence::Prefix)
+            .map(|expr| Expr::Prefix(prefix, Box::new(expr)))
+    }
+
+    /// Parse grouped expression
+    fn parse_grouped_expr(&mut self) -> Option<Expr> {
+        self.next_token();
+
+        let expr = self.parse_expr(Precedence::Lowest)?;
+
+        if !self.consume_token(Token::Rparen) {
+            return None;
+        }
+
+        Some(expr)
+    }
+
+    /// Parse array literal
+    fn parse_array_literal(&mut self) -> Option<Expr> {
+        self.parse_expr_list(Token::Rbracket).map(Expr::Array)
+    }
+
+    /// Parse hash literal
+    fn parse_hash_literal(&mut self) -> Option<Expr> {
+        let mut pairs = vec![];
+
+        while !self.peek_token_is(&Token::Rbrace) {
+            self.next_token();
+            let key = self.parse_expr(Precedence::Lowest)?;
+
+            if !self.consume_token(Token::Colon) {
+                return None;
+            }
+
+            self.next_token();
+            let value = self.parse_expr(Precedence::Lowest)?;
+
+            pairs.push((key, value));
+
+            if !self.peek_token_is(&Token::Rbrace) && !self.consume_token(Token::Comma) {
+                return None;
+            }
+        }
+
+        self.next_token();
+
+        Some(Expr::Hash(pairs))
+    }
+
+    /// Parse if expression, where `else if` chains nest in the alternative
+    fn parse_if_expr(&mut self) -> Option<Expr> {
+        if !self.consume_token(Token::Lparen) {
+            return None;
+        }
+
+        self.next_token();
+
+        let condition = self.parse_expr(Precedence::Lowest)?;
+
+        if !self.consume_token(Token::Rparen) || !self.consume_token(Token::Lbrace) {
+            return None;
+        }
+
+        let consequence = self.parse_block_stmt();
+
+        let mut alternative = None;
+        if self.peek_token_is(&Token::Else) {
+            self.next_token();
+
+            if self.peek_token_is(&Token::If) {
+                self.next_token();
+                alternative = Some(vec![Stmt::Expr(self.parse_if_expr()?)]);
+            } else {
+                if !self.consume_token(Token::Lbrace) {
+                    return None;
+                }
+                alternative = Some(self.parse_block_stmt());
+            }
+        }
+
+        Some(Expr::If {
+            condition: Box::new(condition),
+            consequence,
+            alternative,
+        })
+    }
+
+    /// Parse function literal
+    fn parse_function_literal(&mut self) -> Option<Expr> {
+        if !self.consume_token(Token::Lparen) {
+            return None;
+        }
+
+        let params = self.parse_function_params()?;
+
+        if !self.consume_token(Token::Lbrace) {
+            return None;
+        }
+
+        let body = self.parse_block_stmt();
+
+        Some(Expr::Function { params, body })
+    }
+
+    /// Parse comma-separated parameter names up to `)`
+    fn parse_function_params(&mut self) -> Option<Vec<Ident>> {
+        let mut params = vec![];
+
+        if self.peek_token_is(&Token::Rparen) {
+            self.next_token();
+            return Some(params);
+        }
+
+        self.next_token();
+        params.push(self.parse_param()?);
+
+        while self.peek_token_is(&Token::Comma) {
+            self.next_token();
+            self.next_token();
+            params.push(self.parse_param()?);
+        }
+
+        if !self.consume_token(Token::Rparen) {
+            return None;
+        }
+
+        Some(params)
+    }
+
+    /// Parse a parameter name, recording an error for anything else
+    fn parse_param(&mut self) -> Option<Ident> {
+        let ident = self.parse_ident();
+        if ident.is_none() {
+            self.push_error(format!("expected Ident, got {:?}", self.cur_token));
+        }
+        ident
+    }
+
+    /// Parse block statement, starting at `{`. On a missing `}` the
+    /// statements read so far are returned along with an error.
+    fn parse_block_stmt(&mut self) -> BlockStmt {
+        let mut block = vec![];
+
+        self.next_token();
+
+        while !self.cur_token_is(Token::Rbrace) {
+            if self.cur_token_is(Token::EOF) {
+                self.push_error(format!(
+                    "expected {:?} to close the block, got {:?} instead",
+                    Token::Rbrace,
+                    Token::EOF
+                ));
+                break;
+            }
+            if let Some(stmt) = self.parse_stmt() {
+                block.push(stmt);
+            }
+            self.next_token();
         }
+
+        block
+    }
+
+    /// Parse comma-separated expressions up to `end`, allowing a trailing comma
+    fn parse_expr_list(&mut self, end: Token) -> Option<Vec<Expr>> {
+        let mut list = vec![];
+
+        if self.peek_token_is(&end) {
+            self.next_token();
+            return Some(list);
+        }
+
+        self.next_token();
+        list.push(self.parse_expr(Precedence::Lowest)?);
+
+        while self.peek_token_is(&Token::Comma) {
+            self.next_token();
+            if self.peek_token_is(&end) {
+                break;
+            }
+            self.next_token();
+            list.push(self.parse_expr(Precedence::Lowest)?);
+        }
+
+        if !self.consume_token(end) {
+            return None;
+        }
+
+        Some(list)
     }
 
     /// Parser infix expression
     fn parse_infix_expr(&mut self, left: Expr) -> Option<Expr> {
         let infix = match self.cur_token {
+            Token::Lparen => return self.parse_call_expr(left),
+            Token::Lbracket => return self.parse_index_expr(left),
             Token::Plus => Infix::Plus,
             Token::Minus => Infix::Minus,
             Token::Asterisk => Infix::Asterisk,
@@ -185,12 +436,38 @@ impl Parser {
             _ => return None,
         };
 
+        let precedence = self.cur_precedence();
+
         self.next_token();
 
-        match self.parse_expr(Precedence::Lowest) {
-            Some(expr) => Some(Expr::Infix(infix, Box::new(left), Box::new(expr))),
-            _ => None,
+        self.parse_expr(precedence)
+            .map(|expr| Expr::Infix(infix, Box::new(left), Box::new(expr)))
+    }
+
+    /// Parse call expression, with `left` as the function
+    fn parse_call_expr(&mut self, left: Expr) -> Option<Expr> {
+        let arguments = self.parse_expr_list(Token::Rparen)?;
+
+        Some(Expr::Call {
+            function: Box::new(left),
+            arguments,
+        })
+    }
+
+    /// Parse index expression, with `left` as the indexed object
+    fn parse_index_expr(&mut self, left: Expr) -> Option<Expr> {
+        self.next_token();
+
+        let index = self.parse_expr(Precedence::Lowest)?;
+
+        if !self.consume_token(Token::Rbracket) {
+            return None;
         }
+
+        Some(Expr::Index {
+            object: Box::new(left),
+            index: Box::new(index),
+        })
     }
 
     fn cur_token_is(&self, tok: Token) -> bool {
@@ -201,20 +478,21 @@ impl Parser {
         self.peek_token == *tok
     }
 
-    fn peek_token_is_infix(&self) -> bool {
-        match self.peek_token {
-            Token::Plus
-            | Token::Minus
-            | Token::Asterisk
-            | Token::Slash
-            | Token::Lt
-            | Token::Gt
-            | Token::Equal
-            | Token::NotEqual => true,
-            _ => false,
+    /// Statements may end with an optional semicolon
+    fn skip_semicolon(&mut self) {
+        if self.peek_token_is(&Token::Semicolon) {
+            self.next_token();
         }
     }
 
+    fn cur_precedence(&self) -> Precedence {
+        Precedence::from_token(&self.cur_token)
+    }
+
+    fn peek_precedence(&self) -> Precedence {
+        Precedence::from_token(&self.peek_token)
+    }
+
     fn consume_token(&mut self, tok: Token) -> bool {
         if self.peek_token_is(&tok) {
             self.next_token();
@@ -230,12 +508,45 @@ impl Parser {
             "expected next Some(token to be {:?}, got {:?} instead",
             tok, self.peek_token,
         );
-        self.errors.push(msg);
+        self.push_error(msg);
+    }
+
+    /// Record an error; once `max_errors` are recorded, add a final
+    /// "too many errors" entry. A limit of 0 keeps only that entry.
+    fn push_error(&mut self, msg: String) {
+        if self.too_many_errors() {
+            return;
+        }
+
+        if self.errors.len() < self.max_errors {
+            self.errors.push(msg);
+        }
+        if self.errors.len() == self.max_errors {
+            self.errors.push(TOO_MANY_ERRORS.to_string());
+        }
+    }
+
+    fn too_many_errors(&self) -> bool {
+        self.errors.len() > self.max_errors
     }
 }
 
+/// Variant name of each top-level statement in the input
+pub fn statement_kinds(input: &str) -> Vec<&'static str> {
+    Parser::new(Lexer::new(input))
+        .parse_program()
+        .iter()
+        .map(|stmt| match stmt {
+            Stmt::Blank => "Blank",
+            Stmt::Let(..) => "Let",
+            Stmt::Return(_) => "Return",
+            Stmt::Expr(_) => "Expr",
+        })
+        .collect()
+}
+
 pub fn check_parser_errors(p: &Parser) {
-    if p.errors.len() == 0 {
+    if p.errors.is_empty() {
         return;
     }
 
@@ -251,7 +562,8 @@ mod tests {
     use super::super::*;
     use ast::*;
     use lexer::Lexer;
-    use parser::{check_parser_errors, Parser};
+    use parser::{check_parser_errors, statement_kinds, Parser, DEFAULT_MAX_DEPTH};
+    use token::Token;
 
     #[test]
     fn test_let_statements() {
@@ -489,4 +801,778 @@ return 993322;
             }
         }
     }
+
+    #[test]
+    fn test_max_errors() {
+        let input = "let x 5;\n".repeat(50);
+
+        let mut p = Parser::with_max_errors(Lexer::new(&input), 10);
+        p.parse_program();
+
+        if p.errors().len() != 11 {
+            panic!("errors not capped. expected=11, got={}", p.errors().len());
+        }
+        if p.errors()[10] != "too many errors" {
+            panic!("last error wrong. got={}", p.errors()[10]);
+        }
+
+        let mut p = Parser::with_max_errors(Lexer::new(&input), 0);
+        p.parse_program();
+
+        if p.errors() != ["too many errors"] {
+            panic!(
+                "errors wrong. expected=[\"too many errors\"], got={:?}",
+                p.errors()
+            );
+        }
+
+        let mut p = Parser::with_max_errors(Lexer::new("let x = 1;"), 0);
+        p.parse_program();
+
+        if !p.errors().is_empty() {
+            panic!("errors wrong. expected=[], got={:?}", p.errors());
+        }
+
+        let mut p = Parser::new(Lexer::new(&input));
+        p.parse_program();
+
+        if p.errors().len() != 50 {
+            panic!("errors wrong. expected=50, got={}", p.errors().len());
+        }
+    }
+
+    #[test]
+    fn test_statement_kinds() {
+        let kinds = statement_kinds("let x = 5; x + 1; return x;");
+        let expected = vec!["Let", "Expr", "Return"];
+
+        if kinds != expected {
+            panic!("got={:?}. expected={:?}", kinds, expected);
+        }
+    }
+
+    #[test]
+    fn test_negative_number_at_expr_start() {
+        let tests: Vec<(&str, Vec<Stmt>)> = vec![
+            (
+                "-5;",
+                vec![Stmt::Expr(Expr::Prefix(
+                    Prefix::Minus,
+                    Box::new(Expr::Literal(Literal::Int(5))),
+                ))],
+            ),
+            (
+                "-5 + 3;",
+                vec![Stmt::Expr(Expr::Infix(
+                    Infix::Plus,
+                    Box::new(Expr::Prefix(
+                        Prefix::Minus,
+                        Box::new(Expr::Literal(Literal::Int(5))),
+                    )),
+                    Box::new(Expr::Literal(Literal::Int(3))),
+                ))],
+            ),
+            (
+                "-5 * 3;",
+                vec![Stmt::Expr(Expr::Infix(
+                    Infix::Asterisk,
+                    Box::new(Expr::Prefix(
+                        Prefix::Minus,
+                        Box::new(Expr::Literal(Literal::Int(5))),
+                    )),
+                    Box::new(Expr::Literal(Literal::Int(3))),
+                ))],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            if program != expected {
+                panic!("got={:?}. expected={:?}", program, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_nested_prefix_expr() {
+        let x = || Box::new(Expr::Ident(Ident(String::from("x"))));
+
+        let tests: Vec<(&str, Vec<Stmt>)> = vec![
+            (
+                "!!x;",
+                vec![Stmt::Expr(Expr::Prefix(
+                    Prefix::Bang,
+                    Box::new(Expr::Prefix(Prefix::Bang, x())),
+                ))],
+            ),
+            (
+                "--x;",
+                vec![Stmt::Expr(Expr::Prefix(
+                    Prefix::Minus,
+                    Box::new(Expr::Prefix(Prefix::Minus, x())),
+                ))],
+            ),
+            ("+x;", vec![Stmt::Expr(Expr::Prefix(Prefix::Plus, x()))]),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            if program != expected {
+                panic!("got={:?}. expected={:?}", program, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bool_expr() {
+        let tests: Vec<(&str, Vec<Stmt>)> = vec![
+            (
+                "true;",
+                vec![Stmt::Expr(Expr::Literal(Literal::Bool(true)))],
+            ),
+            (
+                "false;",
+                vec![Stmt::Expr(Expr::Literal(Literal::Bool(false)))],
+            ),
+            (
+                "!true;",
+                vec![Stmt::Expr(Expr::Prefix(
+                    Prefix::Bang,
+                    Box::new(Expr::Literal(Literal::Bool(true))),
+                ))],
+            ),
+            (
+                "true == false;",
+                vec![Stmt::Expr(Expr::Infix(
+                    Infix::Equal,
+                    Box::new(Expr::Literal(Literal::Bool(true))),
+                    Box::new(Expr::Literal(Literal::Bool(false))),
+                ))],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            if program != expected {
+                panic!("got={:?}. expected={:?}", program, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_string_expr() {
+        let input = r#""hello world";"#;
+
+        let mut p = Parser::new(Lexer::new(input));
+        let program = p.parse_program();
+
+        check_parser_errors(&p);
+
+        let tests: Vec<Stmt> = vec![Stmt::Expr(Expr::Literal(Literal::String(String::from(
+            "hello world",
+        ))))];
+
+        if program != tests {
+            panic!("got={:?}. expected={:?}", program, tests);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn test_float_expr() {
+        let tests: Vec<(&str, Vec<Stmt>)> = vec![
+            (
+                "3.14;",
+                vec![Stmt::Expr(Expr::Literal(Literal::Float(3.14)))],
+            ),
+            ("0.5;", vec![Stmt::Expr(Expr::Literal(Literal::Float(0.5)))]),
+            ("1.0;", vec![Stmt::Expr(Expr::Literal(Literal::Float(1.0)))]),
+            (
+                "-3.14;",
+                vec![Stmt::Expr(Expr::Prefix(
+                    Prefix::Minus,
+                    Box::new(Expr::Literal(Literal::Float(3.14))),
+                ))],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            if program != expected {
+                panic!("got={:?}. expected={:?}", program, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_grouped_expr() {
+        let int = |value| Box::new(Expr::Literal(Literal::Int(value)));
+
+        let tests: Vec<(&str, Vec<Stmt>)> = vec![
+            ("(5);", vec![Stmt::Expr(Expr::Literal(Literal::Int(5)))]),
+            ("((1));", vec![Stmt::Expr(Expr::Literal(Literal::Int(1)))]),
+            (
+                "(1 + 2) * 3;",
+                vec![Stmt::Expr(Expr::Infix(
+                    Infix::Asterisk,
+                    Box::new(Expr::Infix(Infix::Plus, int(1), int(2))),
+                    int(3),
+                ))],
+            ),
+            (
+                "((1 + 2));",
+                vec![Stmt::Expr(Expr::Infix(Infix::Plus, int(1), int(2)))],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            if program != expected {
+                panic!("got={:?}. expected={:?}", program, expected);
+            }
+        }
+
+        let grouped = Parser::new(Lexer::new("(1 + 2) * 3;")).parse_program();
+        let ungrouped = Parser::new(Lexer::new("1 + 2 * 3;")).parse_program();
+        if grouped == ungrouped {
+            panic!("grouping had no effect. got={:?}", grouped);
+        }
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        let tests: Vec<(&str, &str)> = vec![
+            ("1 + 2 * 3;", "(+ 1 (* 2 3))"),
+            ("2 * 3 + 1;", "(+ (* 2 3) 1)"),
+            ("1 - 2 - 3;", "(- (- 1 2) 3)"),
+            ("8 / 4 / 2;", "(/ (/ 8 4) 2)"),
+            ("a + b * c - d / e;", "(- (+ a (* b c)) (/ d e))"),
+            ("-a * b;", "(* (- a) b)"),
+            ("!-a;", "(! (- a))"),
+            ("1 + 2 < 4 == true;", "(== (< (+ 1 2) 4) true)"),
+            ("3 > 5 != 3 < 5;", "(!= (> 3 5) (< 3 5))"),
+            ("(1 + 2) * 3;", "(* (+ 1 2) 3)"),
+            ("-(5 + 5);", "(- (+ 5 5))"),
+            ("a + add(b * c) + d;", "(+ (+ a (call add (* b c))) d)"),
+            ("-f(x);", "(- (call f x))"),
+            (
+                "a * [1, 2][b * c] * d;",
+                "(* (* a (index (array 1 2) (* b c))) d)",
+            ),
+            (
+                "add(a[0], f(b)[1]);",
+                "(call add (index a 0) (index (call f b) 1))",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            let sexp = to_sexp(&program);
+            if sexp != expected {
+                panic!(
+                    "{:?} parsed wrong. expected={}, got={}",
+                    input, expected, sexp
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        // A left-associative chain stays at one level however long it is
+        let input = vec!["1"; 10000].join(" + ");
+        let mut parser = Parser::new(Lexer::new(&input));
+        let program = parser.parse_program();
+        check_parser_errors(&parser);
+        if program.len() != 1 {
+            panic!("program has not 1 statement. got={}", program.len());
+        }
+
+        let nested = |open: &str, inner: &str, close: &str, n: usize| {
+            format!("{}{}{}", open.repeat(n), inner, close.repeat(n))
+        };
+
+        let within = nested("(", "1", ")", DEFAULT_MAX_DEPTH - 1);
+        let mut parser = Parser::new(Lexer::new(&within));
+        parser.parse_program();
+        check_parser_errors(&parser);
+
+        let too_deep = format!("expression nested deeper than {} levels", DEFAULT_MAX_DEPTH);
+        let tests: Vec<String> = vec![
+            nested("(", "1", ")", DEFAULT_MAX_DEPTH),
+            nested("(", "1", ")", 10000),
+            nested("-", "1", "", 10000),
+            nested("[", "1", "]", 10000),
+            nested("f(", "1", ")", 10000),
+            nested("fn() { ", "1", " }", 10000),
+            nested("1 + (", "1", ")", 10000),
+        ];
+
+        for input in tests {
+            let mut parser = Parser::new(Lexer::new(&input));
+            parser.parse_program();
+            if parser.errors().first() != Some(&too_deep) {
+                panic!(
+                    "{:.20}... - first error wrong. expected={:?}, got={:?}",
+                    input,
+                    too_deep,
+                    parser.errors().first()
+                );
+            }
+        }
+
+        let mut parser = Parser::new(Lexer::new("((1))")).with_max_depth(3);
+        parser.parse_program();
+        check_parser_errors(&parser);
+
+        let mut parser = Parser::new(Lexer::new("(((1)))")).with_max_depth(3);
+        parser.parse_program();
+        if parser.errors().first().map(String::as_str)
+            != Some("expression nested deeper than 3 levels")
+        {
+            panic!("errors wrong. got={:?}", parser.errors());
+        }
+    }
+
+    #[test]
+    fn test_array_literal() {
+        let int = |value| Expr::Literal(Literal::Int(value));
+
+        let tests: Vec<(&str, Vec<Stmt>)> = vec![
+            ("[];", vec![Stmt::Expr(Expr::Array(vec![]))]),
+            (
+                "[1, 2, 3];",
+                vec![Stmt::Expr(Expr::Array(vec![int(1), int(2), int(3)]))],
+            ),
+            (
+                "[1 + 2, x];",
+                vec![Stmt::Expr(Expr::Array(vec![
+                    Expr::Infix(Infix::Plus, Box::new(int(1)), Box::new(int(2))),
+                    Expr::Ident(Ident(String::from("x"))),
+                ]))],
+            ),
+            (
+                "[[1], [2, 3]];",
+                vec![Stmt::Expr(Expr::Array(vec![
+                    Expr::Array(vec![int(1)]),
+                    Expr::Array(vec![int(2), int(3)]),
+                ]))],
+            ),
+            (
+                "[1, 2,];",
+                vec![Stmt::Expr(Expr::Array(vec![int(1), int(2)]))],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            if program != expected {
+                panic!("got={:?}. expected={:?}", program, expected);
+            }
+        }
+
+        let mut parser = Parser::new(Lexer::new("[1, 2;"));
+        parser.parse_program();
+        if parser.errors.is_empty() {
+            panic!("expected an error for an unterminated array");
+        }
+
+        let tests: Vec<(&str, &str)> = vec![
+            ("[1,", "no prefix parse function for EOF"),
+            ("let a = [1, ;", "no prefix parse function for Semicolon"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            parser.parse_program();
+            if parser.errors().first().map(String::as_str) != Some(expected) {
+                panic!(
+                    "{:?} - first error wrong. expected={:?}, got={:?}",
+                    input,
+                    expected,
+                    parser.errors()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_hash_literal() {
+        let int = |value| Expr::Literal(Literal::Int(value));
+        let string = |value: &str| Expr::Literal(Literal::String(String::from(value)));
+
+        let tests: Vec<(&str, Vec<Stmt>)> = vec![
+            ("{};", vec![Stmt::Expr(Expr::Hash(vec![]))]),
+            (
+                "{1: 2};",
+                vec![Stmt::Expr(Expr::Hash(vec![(int(1), int(2))]))],
+            ),
+            (
+                r#"{"a": true, "b": false};"#,
+                vec![Stmt::Expr(Expr::Hash(vec![
+                    (string("a"), Expr::Literal(Literal::Bool(true))),
+                    (string("b"), Expr::Literal(Literal::Bool(false))),
+                ]))],
+            ),
+            (
+                "{1 + 1: 2 * 3, x: [y]};",
+                vec![Stmt::Expr(Expr::Hash(vec![
+                    (
+                        Expr::Infix(Infix::Plus, Box::new(int(1)), Box::new(int(1))),
+                        Expr::Infix(Infix::Asterisk, Box::new(int(2)), Box::new(int(3))),
+                    ),
+                    (
+                        Expr::Ident(Ident(String::from("x"))),
+                        Expr::Array(vec![Expr::Ident(Ident(String::from("y")))]),
+                    ),
+                ]))],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            if program != expected {
+                panic!("got={:?}. expected={:?}", program, expected);
+            }
+        }
+
+        let mut parser = Parser::new(Lexer::new("{1 2};"));
+        parser.parse_program();
+        if parser.errors.is_empty() {
+            panic!("expected an error for a pair without a colon");
+        }
+
+        let tests: Vec<(&str, &str)> = vec![
+            ("{", "no prefix parse function for EOF"),
+            ("{1:}", "no prefix parse function for Rbrace"),
+            (
+                "{1: 2",
+                "expected next Some(token to be Comma, got EOF instead",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            parser.parse_program();
+            if parser.errors().first().map(String::as_str) != Some(expected) {
+                panic!(
+                    "{:?} - first error wrong. expected={:?}, got={:?}",
+                    input,
+                    expected,
+                    parser.errors()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_function_literal() {
+        let ident = |name: &str| Ident(String::from(name));
+        let ident_expr = |name: &str| Expr::Ident(Ident(String::from(name)));
+
+        let tests: Vec<(&str, Vec<Stmt>)> = vec![
+            (
+                "fn() {};",
+                vec![Stmt::Expr(Expr::Function {
+                    params: vec![],
+                    body: vec![],
+                })],
+            ),
+            (
+                "fn(x) { x };",
+                vec![Stmt::Expr(Expr::Function {
+                    params: vec![ident("x")],
+                    body: vec![Stmt::Expr(ident_expr("x"))],
+                })],
+            ),
+            (
+                "fn(x, y) { x + y; }",
+                vec![Stmt::Expr(Expr::Function {
+                    params: vec![ident("x"), ident("y")],
+                    body: vec![Stmt::Expr(Expr::Infix(
+                        Infix::Plus,
+                        Box::new(ident_expr("x")),
+                        Box::new(ident_expr("y")),
+                    ))],
+                })],
+            ),
+            (
+                "let f = fn(x) { let y = x; return y };",
+                vec![Stmt::Let(
+                    ident("f"),
+                    Expr::Function {
+                        params: vec![ident("x")],
+                        body: vec![
+                            Stmt::Let(ident("y"), ident_expr("x")),
+                            Stmt::Return(ident_expr("y")),
+                        ],
+                    },
+                )],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            if program != expected {
+                panic!("got={:?}. expected={:?}", program, expected);
+            }
+        }
+
+        let tests: Vec<(&str, &str)> = vec![
+            ("fn(1) {}", "expected Ident, got Int(1)"),
+            ("fn(x, 1) {}", "expected Ident, got Int(1)"),
+            ("fn(x,) {}", "expected Ident, got Rparen"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            parser.parse_program();
+            if parser.errors().first().map(String::as_str) != Some(expected) {
+                panic!(
+                    "{:?} - first error wrong. expected={:?}, got={:?}",
+                    input,
+                    expected,
+                    parser.errors()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_block_stmt() {
+        let int = |value| Expr::Literal(Literal::Int(value));
+        let x = || Expr::Ident(Ident(String::from("x")));
+
+        let tests: Vec<(&str, BlockStmt)> = vec![
+            ("{}", vec![]),
+            ("{ 5 }", vec![Stmt::Expr(int(5))]),
+            (
+                "{ let x = 1; return x; x + 2 }",
+                vec![
+                    Stmt::Let(Ident(String::from("x")), int(1)),
+                    Stmt::Return(x()),
+                    Stmt::Expr(Expr::Infix(Infix::Plus, Box::new(x()), Box::new(int(2)))),
+                ],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let block = parser.parse_block_stmt();
+
+            check_parser_errors(&parser);
+
+            if block != expected {
+                panic!("got={:?}. expected={:?}", block, expected);
+            }
+            if !parser.cur_token_is(Token::Rbrace) {
+                panic!("block should end on Rbrace. got={:?}", parser.cur_token);
+            }
+        }
+
+        let mut parser = Parser::new(Lexer::new("{ 5; 6"));
+        let block = parser.parse_block_stmt();
+        if block != vec![Stmt::Expr(int(5)), Stmt::Expr(int(6))] {
+            panic!("partial block wrong. got={:?}", block);
+        }
+        if parser.errors.len() != 1 {
+            panic!(
+                "expected 1 error for a missing Rbrace. got={:?}",
+                parser.errors
+            );
+        }
+    }
+
+    #[test]
+    fn test_if_expr() {
+        let tests: Vec<(&str, &str)> = vec![
+            ("if (x < y) { x }", "(if (< x y) (do x))"),
+            ("if (x < y) { x } else { y }", "(if (< x y) (do x) (do y))"),
+            (
+                "if (a) { if (b) { 1 } else { 2 } }",
+                "(if a (do (if b (do 1) (do 2))))",
+            ),
+            (
+                "if (a) { 1 } else if (b) { 2 } else { 3 }",
+                "(if a (do 1) (do (if b (do 2) (do 3))))",
+            ),
+            (
+                "let x = if (y) { 1 } else { 2 };",
+                "(let x (if y (do 1) (do 2)))",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            let sexp = to_sexp(&program);
+            if sexp != expected {
+                panic!(
+                    "{:?} parsed wrong. expected={}, got={}",
+                    input, expected, sexp
+                );
+            }
+        }
+
+        let program = Parser::new(Lexer::new("if (x) { 1 } else { 2 }")).parse_program();
+        let expected = vec![Stmt::Expr(Expr::If {
+            condition: Box::new(Expr::Ident(Ident(String::from("x")))),
+            consequence: vec![Stmt::Expr(Expr::Literal(Literal::Int(1)))],
+            alternative: Some(vec![Stmt::Expr(Expr::Literal(Literal::Int(2)))]),
+        })];
+        if program != expected {
+            panic!("got={:?}. expected={:?}", program, expected);
+        }
+    }
+
+    #[test]
+    fn test_call_expr() {
+        let tests: Vec<(&str, &str)> = vec![
+            ("add(1, 2);", "(call add 1 2)"),
+            ("fn(x) { x }(5);", "(call (fn (x) x) 5)"),
+            ("f();", "(call f)"),
+            ("add(1 + 2, x * 3);", "(call add (+ 1 2) (* x 3))"),
+            ("f(1)(2);", "(call (call f 1) 2)"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            let sexp = to_sexp(&program);
+            if sexp != expected {
+                panic!(
+                    "{:?} parsed wrong. expected={}, got={}",
+                    input, expected, sexp
+                );
+            }
+        }
+
+        let program = Parser::new(Lexer::new("add(1, x);")).parse_program();
+        let expected = vec![Stmt::Expr(Expr::Call {
+            function: Box::new(Expr::Ident(Ident(String::from("add")))),
+            arguments: vec![
+                Expr::Literal(Literal::Int(1)),
+                Expr::Ident(Ident(String::from("x"))),
+            ],
+        })];
+        if program != expected {
+            panic!("got={:?}. expected={:?}", program, expected);
+        }
+
+        let tests: Vec<(&str, &str)> = vec![
+            ("f(1,", "no prefix parse function for EOF"),
+            ("f(, 1)", "no prefix parse function for Comma"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            parser.parse_program();
+            if parser.errors().first().map(String::as_str) != Some(expected) {
+                panic!(
+                    "{:?} - first error wrong. expected={:?}, got={:?}",
+                    input,
+                    expected,
+                    parser.errors()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_index_expr() {
+        let tests: Vec<(&str, &str)> = vec![
+            ("arr[0];", "(index arr 0)"),
+            ("arr[1 + 2];", "(index arr (+ 1 2))"),
+            ("[[1, 2]][0];", "(index (array (array 1 2)) 0)"),
+            ("arr[0][1];", "(index (index arr 0) 1)"),
+            (r#"{"a": 1}["a"];"#, r#"(index (hash ("a" 1)) "a")"#),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            check_parser_errors(&parser);
+
+            let sexp = to_sexp(&program);
+            if sexp != expected {
+                panic!(
+                    "{:?} parsed wrong. expected={}, got={}",
+                    input, expected, sexp
+                );
+            }
+        }
+
+        let program = Parser::new(Lexer::new("arr[0];")).parse_program();
+        let expected = vec![Stmt::Expr(Expr::Index {
+            object: Box::new(Expr::Ident(Ident(String::from("arr")))),
+            index: Box::new(Expr::Literal(Literal::Int(0))),
+        })];
+        if program != expected {
+            panic!("got={:?}. expected={:?}", program, expected);
+        }
+
+        let tests: Vec<(&str, &str)> = vec![
+            ("x[", "no prefix parse function for EOF"),
+            (
+                "x[1",
+                "expected next Some(token to be Rbracket, got EOF instead",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            parser.parse_program();
+            if parser.errors().first().map(String::as_str) != Some(expected) {
+                panic!(
+                    "{:?} - first error wrong. expected={:?}, got={:?}",
+                    input,
+                    expected,
+                    parser.errors()
+                );
+            }
+        }
+    }
 }
@@ -6,6 +6,8 @@ pub enum Token {
     // Identifier + Literal
     Ident(String),
     Int(i64),
+    Float(f64),
+    Str(String),
 
     // Operator
     Assign,
@@ -23,11 +25,14 @@ pub enum Token {
     // Delimiter
     Comma,
     Semicolon,
+    Colon,
 
     Lparen,
     Rparen,
     Lbrace,
     Rbrace,
+    Lbracket,
+    Rbracket,
 
     // Keyword
     Fn,
@@ -38,3 +43,130 @@ pub enum Token {
     Else,
     Return,
 }
+
+impl Token {
+    /// Compare the token variants, ignoring any literal payload
+    pub fn same_kind(&self, other: &Token) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// Name of the variant, ignoring any literal payload
+    pub fn kind_name(&self) -> &'static str {
+        // Exhaustive on purpose: adding or renaming a variant must be
+        // reflected here, keeping the lexer, parser and token names in sync.
+        match self {
+            Token::Illegal => "Illegal",
+            Token::EOF => "EOF",
+            Token::Ident(_) => "Ident",
+            Token::Int(_) => "Int",
+            Token::Float(_) => "Float",
+            Token::Str(_) => "Str",
+            Token::Assign => "Assign",
+            Token::Plus => "Plus",
+            Token::Minus => "Minus",
+            Token::Bang => "Bang",
+            Token::Asterisk => "Asterisk",
+            Token::Slash => "Slash",
+            Token::Equal => "Equal",
+            Token::NotEqual => "NotEqual",
+            Token::Lt => "Lt",
+            Token::Gt => "Gt",
+            Token::Comma => "Comma",
+            Token::Semicolon => "Semicolon",
+            Token::Colon => "Colon",
+            Token::Lparen => "Lparen",
+            Token::Rparen => "Rparen",
+            Token::Lbrace => "Lbrace",
+            Token::Rbrace => "Rbrace",
+            Token::Lbracket => "Lbracket",
+            Token::Rbracket => "Rbracket",
+            Token::Fn => "Fn",
+            Token::Let => "Let",
+            Token::True => "True",
+            Token::False => "False",
+            Token::If => "If",
+            Token::Else => "Else",
+            Token::Return => "Return",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_kind() {
+        let tests: Vec<(Token, Token, bool)> = vec![
+            (
+                Token::Ident(String::from("a")),
+                Token::Ident(String::from("b")),
+                true,
+            ),
+            (Token::Int(1), Token::Int(2), true),
+            (Token::Plus, Token::Plus, true),
+            (Token::Ident(String::from("a")), Token::Int(1), false),
+            (Token::Lt, Token::Gt, false),
+            (Token::Int(5), Token::EOF, false),
+        ];
+
+        for (a, b, expected) in tests {
+            if a.same_kind(&b) != expected {
+                panic!("{:?}.same_kind({:?}) expected={}", a, b, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_token_names() {
+        let tests: Vec<(Token, &str)> = vec![
+            (Token::Illegal, "Illegal"),
+            (Token::EOF, "EOF"),
+            (Token::Ident(String::from("x")), "Ident"),
+            (Token::Int(1), "Int"),
+            (Token::Float(1.5), "Float"),
+            (Token::Str(String::from("a")), "Str"),
+            (Token::Assign, "Assign"),
+            (Token::Plus, "Plus"),
+            (Token::Minus, "Minus"),
+            (Token::Bang, "Bang"),
+            (Token::Asterisk, "Asterisk"),
+            (Token::Slash, "Slash"),
+            (Token::Equal, "Equal"),
+            (Token::NotEqual, "NotEqual"),
+            (Token::Lt, "Lt"),
+            (Token::Gt, "Gt"),
+            (Token::Comma, "Comma"),
+            (Token::Semicolon, "Semicolon"),
+            (Token::Colon, "Colon"),
+            (Token::Lparen, "Lparen"),
+            (Token::Rparen, "Rparen"),
+            (Token::Lbrace, "Lbrace"),
+            (Token::Rbrace, "Rbrace"),
+            (Token::Lbracket, "Lbracket"),
+            (Token::Rbracket, "Rbracket"),
+            (Token::Fn, "Fn"),
+            (Token::Let, "Let"),
+            (Token::True, "True"),
+            (Token::False, "False"),
+            (Token::If, "If"),
+            (Token::Else, "Else"),
+            (Token::Return, "Return"),
+        ];
+
+        for (tok, expected) in tests {
+            if tok.kind_name() != expected {
+                panic!(
+                    "{:?}.kind_name() wrong. expected={}, got={}",
+                    tok,
+                    expected,
+                    tok.kind_name()
+                );
+            }
+            // The name is the variant name, as printed by Debug
+            if !format!("{:?}", tok).starts_with(expected) {
+                panic!("{:?} does not start with {}", tok, expected);
+            }
+        }
+    }
+}